@@ -0,0 +1,407 @@
+//! Just enough of the Source engine's VBSP format to get a map's world geometry as triangles.
+//!
+//! Only the visible faces of the world brushes and displacements are read. Static props, brush
+//! entities and tool brushes such as player clips are left out.
+
+use crate::{Result, Vec3};
+use std::{fmt::Display, ops::Range, path::Path};
+
+const IDENT: &[u8; 4] = b"VBSP";
+const LUMP_COUNT: usize = 64;
+const HEADER_SIZE: usize = 8 + LUMP_COUNT * 16 + 4;
+
+const LUMP_VERTEXES: usize = 3;
+const LUMP_TEXINFO: usize = 6;
+const LUMP_FACES: usize = 7;
+const LUMP_EDGES: usize = 12;
+const LUMP_SURFEDGES: usize = 13;
+const LUMP_MODELS: usize = 14;
+const LUMP_DISPINFO: usize = 26;
+const LUMP_DISP_VERTS: usize = 33;
+
+const TEXINFO_SIZE: usize = 72;
+const FACE_SIZE: usize = 56;
+const MODEL_SIZE: usize = 48;
+const DISPINFO_SIZE: usize = 176;
+const DISP_VERT_SIZE: usize = 20;
+
+// Surfaces that don't block anything: skybox, tool textures and the like.
+const SURF_SKY2D: i32 = 0x2;
+const SURF_SKY: i32 = 0x4;
+const SURF_TRIGGER: i32 = 0x40;
+const SURF_NODRAW: i32 = 0x80;
+const SURF_HINT: i32 = 0x100;
+const SURF_SKIP: i32 = 0x200;
+const SURF_IGNORED: i32 =
+    SURF_SKY2D | SURF_SKY | SURF_TRIGGER | SURF_NODRAW | SURF_HINT | SURF_SKIP;
+
+/// A map's world as a triangle mesh.
+#[derive(Debug, Default)]
+pub struct MapGeometry {
+    pub vertices: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+struct Bsp<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Bsp<'a> {
+    fn lump(&self, index: usize) -> Result<&'a [u8]> {
+        let entry = 8 + index * 16;
+        let offset = to_index(read_i32(self.data, entry)?, "lump offset")?;
+        let length = to_index(read_i32(self.data, entry + 4)?, "lump length")?;
+        let Some(lump) = span(offset, length).ok().and_then(|r| self.data.get(r)) else {
+            return Err(format!("BSP lump {index} is out of bounds").into());
+        };
+        if lump.starts_with(b"LZMA") {
+            return Err(format!("BSP lump {index} is compressed, which is unsupported").into());
+        }
+        Ok(lump)
+    }
+}
+
+// Offsets and counts in the file are signed, and anything could be in them, so every one is
+// checked before it's used to index or size anything.
+fn to_index<T: Copy + Display>(value: T, what: &str) -> Result<usize>
+where
+    usize: TryFrom<T>,
+{
+    usize::try_from(value).map_err(|_| format!("BSP {what} {value} is out of range").into())
+}
+
+fn span(first: usize, count: usize) -> Result<Range<usize>> {
+    first
+        .checked_add(count)
+        .map(|end| first..end)
+        .ok_or_else(|| format!("BSP range of {count} from {first} overflows").into())
+}
+
+// The `index`th of a lump's fixed size records.
+fn record(data: &[u8], index: usize, size: usize) -> Result<&[u8]> {
+    index
+        .checked_mul(size)
+        .and_then(|start| data.get(start..)?.get(..size))
+        .ok_or_else(|| format!("BSP record {index} of size {size} is out of bounds").into())
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    offset
+        .checked_add(N)
+        .and_then(|end| data.get(offset..end))
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| format!("BSP read out of bounds at {offset}").into())
+}
+
+fn read_i32(data: &[u8], offset: usize) -> Result<i32> {
+    read_bytes(data, offset).map(i32::from_le_bytes)
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Result<i16> {
+    read_bytes(data, offset).map(i16::from_le_bytes)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    read_bytes(data, offset).map(u16::from_le_bytes)
+}
+
+fn read_f32(data: &[u8], offset: usize) -> Result<f32> {
+    read_bytes(data, offset).map(f32::from_le_bytes)
+}
+
+fn read_vec(data: &[u8], offset: usize) -> Result<Vec3> {
+    Ok(Vec3::new(
+        read_f32(data, offset)?,
+        read_f32(data, offset + 4)?,
+        read_f32(data, offset + 8)?,
+    ))
+}
+
+pub fn load(path: &Path) -> Result<MapGeometry> {
+    parse(&std::fs::read(path)?)
+}
+
+pub fn parse(data: &[u8]) -> Result<MapGeometry> {
+    if data.len() < HEADER_SIZE || &data[0..4] != IDENT {
+        return Err("Not a VBSP file".into());
+    }
+    let bsp = Bsp { data };
+
+    let vertexes = bsp.lump(LUMP_VERTEXES)?;
+    let texinfo = bsp.lump(LUMP_TEXINFO)?;
+    let faces = bsp.lump(LUMP_FACES)?;
+    let edges = bsp.lump(LUMP_EDGES)?;
+    let surfedges = bsp.lump(LUMP_SURFEDGES)?;
+    let models = bsp.lump(LUMP_MODELS)?;
+    let dispinfo = bsp.lump(LUMP_DISPINFO)?;
+    let disp_verts = bsp.lump(LUMP_DISP_VERTS)?;
+
+    // Model 0 is the world; the rest belong to brush entities such as doors.
+    if models.len() < MODEL_SIZE {
+        return Err("BSP has no world model".into());
+    }
+    let first_face = to_index(read_i32(models, 40)?, "first face")?;
+    let face_count = to_index(read_i32(models, 44)?, "face count")?;
+
+    let mut geometry = MapGeometry::default();
+    for f in span(first_face, face_count)? {
+        let face = record(faces, f, FACE_SIZE)?;
+        let first_edge = to_index(read_i32(face, 4)?, "first edge")?;
+        let edge_count = to_index(read_i16(face, 8)?, "edge count")?;
+        let tex = read_i16(face, 10)?;
+        let disp = read_i16(face, 12)?;
+
+        if tex >= 0 {
+            let texinfo = record(texinfo, to_index(tex, "texinfo")?, TEXINFO_SIZE)?;
+            if read_i32(texinfo, 64)? & SURF_IGNORED != 0 {
+                continue;
+            }
+        }
+
+        let mut corners = Vec::with_capacity(edge_count);
+        for s in span(first_edge, edge_count)? {
+            let surfedge = read_i32(record(surfedges, s, 4)?, 0)?;
+            let edge = record(edges, to_index(surfedge.unsigned_abs(), "edge")?, 4)?;
+            // Negative surfedges walk the edge backwards.
+            let v = if surfedge >= 0 {
+                read_u16(edge, 0)?
+            } else {
+                read_u16(edge, 2)?
+            };
+            corners.push(read_vec(record(vertexes, v.into(), 12)?, 0)?);
+        }
+
+        if disp >= 0 {
+            let info = record(dispinfo, to_index(disp, "displacement")?, DISPINFO_SIZE)?;
+            add_displacement(&mut geometry, &corners, info, disp_verts)?;
+        } else {
+            add_polygon(&mut geometry, &corners);
+        }
+    }
+
+    Ok(geometry)
+}
+
+// Faces are convex, so a fan covers them.
+fn add_polygon(geometry: &mut MapGeometry, corners: &[Vec3]) {
+    if corners.len() < 3 {
+        return;
+    }
+    let base = geometry.vertices.len() as u32;
+    geometry.vertices.extend_from_slice(corners);
+    for i in 1..corners.len() as u32 - 1 {
+        geometry.indices.push([base, base + i, base + i + 1]);
+    }
+}
+
+// A displacement is a grid stretched over its four sided face, with every point then pushed
+// along its own offset.
+fn add_displacement(
+    geometry: &mut MapGeometry,
+    corners: &[Vec3],
+    info: &[u8],
+    disp_verts: &[u8],
+) -> Result<()> {
+    if corners.len() != 4 {
+        return Err("BSP displacement on a face without 4 sides".into());
+    }
+    let start = read_vec(info, 0)?;
+    let first_vert = to_index(read_i32(info, 12)?, "first displacement vertex")?;
+    let power = read_i32(info, 20)?;
+    if !(2..=4).contains(&power) {
+        return Err(format!("BSP displacement with unexpected power {power}").into());
+    }
+    let size = (1usize << power) + 1;
+
+    // The grid starts at the corner nearest startPosition.
+    let first = (0..4)
+        .min_by(|a, b| {
+            let da = (corners[*a] - start).norm_squared();
+            let db = (corners[*b] - start).norm_squared();
+            da.total_cmp(&db)
+        })
+        .unwrap_or(0);
+    let c: Vec<Vec3> = (0..4).map(|i| corners[(first + i) % 4]).collect();
+
+    let base = geometry.vertices.len() as u32;
+    let step = (size - 1) as f32;
+    for i in 0..size {
+        let left = c[0] + (c[1] - c[0]) * (i as f32 / step);
+        let right = c[3] + (c[2] - c[3]) * (i as f32 / step);
+        for j in 0..size {
+            let Some(v) = first_vert.checked_add(i * size + j) else {
+                return Err("BSP displacement vertex out of range".into());
+            };
+            let vert = record(disp_verts, v, DISP_VERT_SIZE)?;
+            let offset = read_vec(vert, 0)?.coords * read_f32(vert, 12)?;
+            geometry
+                .vertices
+                .push(left + (right - left) * (j as f32 / step) + offset);
+        }
+    }
+
+    for i in 0..size as u32 - 1 {
+        for j in 0..size as u32 - 1 {
+            let v = base + i * size as u32 + j;
+            let below = v + size as u32;
+            geometry.indices.push([v, below, v + 1]);
+            geometry.indices.push([v + 1, below, below + 1]);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes<T: Copy, const N: usize>(values: &[T], to_bytes: fn(T) -> [u8; N]) -> Vec<u8> {
+        values.iter().flat_map(|v| to_bytes(*v)).collect()
+    }
+
+    // A file with the given lumps laid out one after another following the header.
+    fn file(lumps: &[(usize, Vec<u8>)]) -> Vec<u8> {
+        let mut header = vec![0; HEADER_SIZE];
+        header[0..4].copy_from_slice(IDENT);
+        let mut data = vec![];
+        for (index, lump) in lumps {
+            let entry = 8 + index * 16;
+            let offset = (HEADER_SIZE + data.len()) as i32;
+            header[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+            header[entry + 4..entry + 8].copy_from_slice(&(lump.len() as i32).to_le_bytes());
+            data.extend_from_slice(lump);
+        }
+        header.extend(data);
+        header
+    }
+
+    fn set_i32(data: &mut [u8], offset: usize, value: i32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn face(tex: i16, disp: i16) -> Vec<u8> {
+        let mut face = vec![0; FACE_SIZE];
+        set_i32(&mut face, 4, 0); // first surfedge
+        face[8..10].copy_from_slice(&4i16.to_le_bytes());
+        face[10..12].copy_from_slice(&tex.to_le_bytes());
+        face[12..14].copy_from_slice(&disp.to_le_bytes());
+        face
+    }
+
+    fn model(first_face: i32, face_count: i32) -> Vec<u8> {
+        let mut model = vec![0; MODEL_SIZE];
+        set_i32(&mut model, 40, first_face);
+        set_i32(&mut model, 44, face_count);
+        model
+    }
+
+    // A square floor drawn three times: once plainly, once with a nodraw texture and once as a
+    // flat displacement.
+    fn lumps() -> Vec<(usize, Vec<u8>)> {
+        let vertexes = [
+            0.0, 0.0, 0.0, 64.0, 0.0, 0.0, 64.0, 64.0, 0.0, 0.0, 64.0, 0.0,
+        ];
+        let mut texinfo = vec![0; TEXINFO_SIZE * 2];
+        set_i32(&mut texinfo, TEXINFO_SIZE + 64, SURF_NODRAW);
+        let mut dispinfo = vec![0; DISPINFO_SIZE];
+        set_i32(&mut dispinfo, 20, 2); // power, so a 5x5 grid
+        let mut disp_verts = vec![];
+        for _ in 0..25 {
+            disp_verts.extend(bytes(&[0.0, 0.0, 1.0, 0.0, 0.0], f32::to_le_bytes));
+        }
+
+        vec![
+            (LUMP_VERTEXES, bytes(&vertexes, f32::to_le_bytes)),
+            (LUMP_TEXINFO, texinfo),
+            (LUMP_FACES, [face(0, -1), face(1, -1), face(-1, 0)].concat()),
+            // The last edge is stored backwards, and walked with a negative surfedge.
+            (
+                LUMP_EDGES,
+                bytes(&[0u16, 0, 0, 1, 1, 2, 2, 3, 0, 3], u16::to_le_bytes),
+            ),
+            (LUMP_SURFEDGES, bytes(&[1, 2, 3, -4], i32::to_le_bytes)),
+            (LUMP_MODELS, model(0, 3)),
+            (LUMP_DISPINFO, dispinfo),
+            (LUMP_DISP_VERTS, disp_verts),
+        ]
+    }
+
+    fn with_lump(index: usize, lump: Vec<u8>) -> Vec<u8> {
+        let mut lumps = lumps();
+        lumps.iter_mut().find(|(i, _)| *i == index).unwrap().1 = lump;
+        file(&lumps)
+    }
+
+    #[test]
+    fn parses_faces_and_displacements() {
+        let geometry = parse(&file(&lumps())).unwrap();
+        assert_eq!(geometry.vertices.len(), 4 + 25);
+        assert_eq!(geometry.indices.len(), 2 + 32);
+        assert_eq!(geometry.indices[0], [0, 1, 2]);
+        assert_eq!(geometry.indices[1], [0, 2, 3]);
+        assert_eq!(
+            geometry.vertices[..4],
+            [
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(64.0, 0.0, 0.0),
+                Vec3::new(64.0, 64.0, 0.0),
+                Vec3::new(0.0, 64.0, 0.0),
+            ]
+        );
+        assert_eq!(geometry.vertices[4 + 6], Vec3::new(16.0, 16.0, 0.0));
+        assert!(
+            geometry
+                .indices
+                .iter()
+                .flatten()
+                .all(|&i| (i as usize) < 29)
+        );
+    }
+
+    #[test]
+    fn rejects_empty_and_foreign_files() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(b"VBSP").is_err());
+        let mut data = file(&lumps());
+        data[0..4].copy_from_slice(b"IBSP");
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_lumps_out_of_bounds() {
+        for (offset, length) in [
+            (i32::MAX, 1),
+            (0, i32::MAX),
+            (-1, 4),
+            (0, -1),
+            (i32::MAX, i32::MAX),
+        ] {
+            let mut data = file(&lumps());
+            set_i32(&mut data, 8 + LUMP_FACES * 16, offset);
+            set_i32(&mut data, 8 + LUMP_FACES * 16 + 4, length);
+            assert!(parse(&data).is_err(), "offset {offset} length {length}");
+        }
+        let mut data = file(&lumps());
+        data.truncate(data.len() - 1);
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_indices() {
+        for (first, count) in [(i32::MAX, i32::MAX), (0, -1), (-1, 1), (0, 4)] {
+            assert!(parse(&with_lump(LUMP_MODELS, model(first, count))).is_err());
+        }
+        assert!(parse(&with_lump(LUMP_MODELS, vec![])).is_err());
+        for surfedge in [i32::MIN, i32::MAX, 5] {
+            let surfedges = bytes(&[1, 2, 3, surfedge], i32::to_le_bytes);
+            assert!(parse(&with_lump(LUMP_SURFEDGES, surfedges)).is_err());
+        }
+        assert!(parse(&with_lump(LUMP_TEXINFO, vec![0; TEXINFO_SIZE])).is_err());
+        assert!(parse(&with_lump(LUMP_FACES, face(0, i16::MAX))).is_err());
+        let mut dispinfo = vec![0; DISPINFO_SIZE];
+        set_i32(&mut dispinfo, 12, i32::MAX);
+        set_i32(&mut dispinfo, 20, 2);
+        assert!(parse(&with_lump(LUMP_DISPINFO, dispinfo)).is_err());
+    }
+}
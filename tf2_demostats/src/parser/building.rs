@@ -0,0 +1,768 @@
+use crate::{
+    Vec3,
+    parser::{
+        entity::Entity, is_false, is_zero, is_zero_f32, region::RegionMap, weapon::SentryFire,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tf_demo_parser::demo::{
+    data::DemoTick, message::packetentities::EntityId, parser::gamestateanalyser::Team,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildingKind {
+    Sentry,
+    Dispenser,
+    Teleporter,
+}
+
+/// A building's state as of its latest entity update.
+pub struct BuildingState {
+    pub kind: BuildingKind,
+    pub level: u32,
+    pub mini: bool,
+    pub carried: bool,
+    pub shielded: bool, // a wrangled sentry
+    pub origin: Vec3,
+}
+
+impl BuildingState {
+    pub fn of(e: &dyn Entity) -> Option<Self> {
+        let (kind, level, mini, carried, shielded, origin) = if let Some(b) = e.sentry() {
+            let (mini, shielded) = (b.is_mini, b.shielded);
+            (
+                BuildingKind::Sentry,
+                b.level,
+                mini,
+                b.carried,
+                shielded,
+                b.origin,
+            )
+        } else if let Some(b) = e.dispenser() {
+            (
+                BuildingKind::Dispenser,
+                b.level,
+                false,
+                b.carried,
+                false,
+                b.origin,
+            )
+        } else if let Some(b) = e.teleporter() {
+            (
+                BuildingKind::Teleporter,
+                b.level,
+                false,
+                b.carried,
+                false,
+                b.origin,
+            )
+        } else {
+            return None;
+        };
+        Some(Self {
+            kind,
+            level,
+            mini,
+            carried,
+            shielded,
+            origin,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LevelChange {
+    pub tick: DemoTick,
+    pub level: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Placement {
+    pub tick: DemoTick,
+    pub origin: Vec3,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+/// One building, from being placed until it was destroyed or otherwise removed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuildingRecord {
+    pub kind: BuildingKind,
+    pub owner: String, // steamid
+    pub team: Team,
+    #[serde(skip_serializing_if = "is_false")]
+    pub mini: bool,
+    pub origin: Vec3, // where it was first placed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>, // callout for the origin
+
+    pub built_tick: DemoTick,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_tick: Option<DemoTick>, // none if still standing when the round ended
+    pub lifetime: f32, // in seconds
+    pub levels: Vec<LevelChange>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destroyed_tick: Option<DemoTick>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destroyed_by: Option<String>, // steamid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destroyed_with: Option<String>, // weapon
+
+    // sentries only
+    #[serde(skip_serializing_if = "is_zero")]
+    pub kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub wrangled_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub wrangled_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub rocket_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub rocket_damage: u32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub shielded_time: f32, // in seconds, behind the wrangler's shield
+
+    #[serde(skip_serializing_if = "is_zero")]
+    pub times_carried: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redeploys: Vec<Placement>, // where it was put down after each carry
+
+    #[serde(skip)]
+    entity: EntityId,
+    #[serde(skip)]
+    carried: bool,
+    #[serde(skip)]
+    shielded_since: Option<DemoTick>,
+}
+
+impl BuildingRecord {
+    pub fn new(
+        entity: EntityId,
+        owner: String,
+        team: Team,
+        tick: DemoTick,
+        state: &BuildingState,
+        region: Option<String>,
+    ) -> Self {
+        Self {
+            kind: state.kind,
+            owner,
+            team,
+            mini: state.mini,
+            origin: state.origin,
+            region,
+            built_tick: tick,
+            removed_tick: None,
+            lifetime: 0.0,
+            levels: vec![LevelChange {
+                tick,
+                level: state.level,
+            }],
+            destroyed_tick: None,
+            destroyed_by: None,
+            destroyed_with: None,
+            kills: 0,
+            damage: 0,
+            wrangled_kills: 0,
+            wrangled_damage: 0,
+            rocket_kills: 0,
+            rocket_damage: 0,
+            shielded_time: 0.0,
+            times_carried: 0,
+            redeploys: vec![],
+            entity,
+            carried: state.carried,
+            shielded_since: state.shielded.then_some(tick),
+        }
+    }
+
+    fn update(
+        &mut self,
+        tick: DemoTick,
+        state: &BuildingState,
+        interval_per_tick: f32,
+        regions: Option<&RegionMap>,
+    ) {
+        if self.levels.last().is_some_and(|l| l.level != state.level) {
+            self.levels.push(LevelChange {
+                tick,
+                level: state.level,
+            });
+        }
+
+        if state.carried && !self.carried {
+            self.times_carried += 1;
+        } else if !state.carried && self.carried {
+            self.redeploys.push(Placement {
+                tick,
+                origin: state.origin,
+                region: regions
+                    .and_then(|r| r.region_at(&state.origin))
+                    .map(String::from),
+            });
+        }
+        self.carried = state.carried;
+
+        if state.shielded && self.shielded_since.is_none() {
+            self.shielded_since = Some(tick);
+        } else if !state.shielded {
+            self.end_shield(tick, interval_per_tick);
+        }
+    }
+
+    fn end_shield(&mut self, tick: DemoTick, interval_per_tick: f32) {
+        if let Some(since) = self.shielded_since.take() {
+            let ticks = u32::from(tick).saturating_sub(u32::from(since));
+            self.shielded_time += ticks as f32 * interval_per_tick;
+        }
+    }
+
+    fn finish(&mut self, tick: DemoTick, interval_per_tick: f32) {
+        let ticks = u32::from(tick).saturating_sub(u32::from(self.built_tick));
+        self.lifetime = ticks as f32 * interval_per_tick;
+        self.end_shield(tick, interval_per_tick);
+    }
+
+    fn handle_destroyed(&mut self, tick: DemoTick, attacker: Option<String>, weapon: &str) {
+        self.destroyed_tick = Some(tick);
+        self.destroyed_by = attacker;
+        self.destroyed_with = Some(weapon.to_string());
+    }
+
+    pub fn handle_sentry_damage(&mut self, damage: u32, fire: SentryFire) {
+        self.damage += damage;
+        if fire.wrangled {
+            self.wrangled_damage += damage;
+        }
+        if fire.rocket {
+            self.rocket_damage += damage;
+        }
+    }
+
+    pub fn handle_sentry_kill(&mut self, fire: SentryFire) {
+        self.kills += 1;
+        if fire.wrangled {
+            self.wrangled_kills += 1;
+        }
+        if fire.rocket {
+            self.rocket_kills += 1;
+        }
+    }
+}
+
+/// Buildings that are still standing, keyed by entity.
+#[derive(Debug, Default)]
+pub struct BuildingTracker {
+    live: HashMap<EntityId, BuildingRecord>,
+}
+
+impl BuildingTracker {
+    pub fn handle_built(&mut self, record: BuildingRecord) {
+        self.live.insert(record.entity, record);
+    }
+
+    pub fn handle_update(
+        &mut self,
+        entity: EntityId,
+        tick: DemoTick,
+        state: &BuildingState,
+        interval_per_tick: f32,
+        regions: Option<&RegionMap>,
+    ) {
+        if let Some(record) = self.live.get_mut(&entity) {
+            record.update(tick, state, interval_per_tick, regions);
+        }
+    }
+
+    // Sentry fire can be reported after the sentry's entity is gone, so this falls back to the
+    // sentries removed this tick.
+    pub fn sentry<'a>(
+        &'a mut self,
+        removed: &'a mut [BuildingRecord],
+        entity: EntityId,
+        tick: DemoTick,
+    ) -> Option<&'a mut BuildingRecord> {
+        match self.live.get_mut(&entity) {
+            Some(record) => Some(record),
+            None => just_removed(removed, entity, tick),
+        }
+        .filter(|b| b.kind == BuildingKind::Sentry)
+    }
+
+    // Returns false if the building isn't being tracked.
+    pub fn handle_destroyed(
+        &mut self,
+        entity: EntityId,
+        tick: DemoTick,
+        attacker: Option<String>,
+        weapon: &str,
+    ) -> bool {
+        let Some(record) = self.live.get_mut(&entity) else {
+            return false;
+        };
+        record.handle_destroyed(tick, attacker, weapon);
+        true
+    }
+
+    pub fn handle_removed(
+        &mut self,
+        entity: EntityId,
+        tick: DemoTick,
+        interval_per_tick: f32,
+    ) -> Option<BuildingRecord> {
+        let mut record = self.live.remove(&entity)?;
+        record.removed_tick = Some(tick);
+        record.finish(tick, interval_per_tick);
+        Some(record)
+    }
+
+    // Hands back every building still standing, which stop being tracked.
+    pub fn take_standing(&mut self, tick: DemoTick, interval_per_tick: f32) -> Vec<BuildingRecord> {
+        let mut standing: Vec<_> = self.live.drain().map(|(_, b)| b).collect();
+        for record in &mut standing {
+            record.finish(tick, interval_per_tick);
+        }
+        standing.sort_by_key(|b| b.built_tick);
+        standing
+    }
+}
+
+// Events about a building can arrive after its entity is gone, on the same tick.
+fn just_removed(
+    records: &mut [BuildingRecord],
+    entity: EntityId,
+    tick: DemoTick,
+) -> Option<&mut BuildingRecord> {
+    records
+        .iter_mut()
+        .rev()
+        .find(|b| b.entity == entity && b.removed_tick == Some(tick))
+}
+
+pub fn handle_late_destruction(
+    records: &mut [BuildingRecord],
+    entity: EntityId,
+    tick: DemoTick,
+    attacker: Option<String>,
+    weapon: &str,
+) {
+    if let Some(record) = just_removed(records, entity, tick) {
+        record.handle_destroyed(tick, attacker, weapon);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SapTarget {
+    Building(BuildingKind),
+    Robot, // MvM
+}
+
+/// One sapper, from being placed until it came off or what it was on went down.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SapperRecord {
+    pub spy: String, // steamid
+    pub team: Team,
+    pub target: SapTarget,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_owner: Option<String>, // steamid of the engineer, or the robot itself
+
+    pub placed_tick: DemoTick,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_tick: Option<DemoTick>, // none if still on when the round ended
+    pub lifetime: f32, // in seconds
+
+    #[serde(skip_serializing_if = "is_false")]
+    pub target_destroyed: bool, // what it was on was destroyed while sapped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_by: Option<String>, // steamid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_with: Option<String>, // weapon
+
+    #[serde(skip)]
+    entity: EntityId,
+    #[serde(skip)]
+    target_entity: EntityId,
+}
+
+impl SapperRecord {
+    pub fn new(
+        entity: EntityId,
+        spy: String,
+        team: Team,
+        target: SapTarget,
+        target_entity: EntityId,
+        target_owner: Option<String>,
+        tick: DemoTick,
+    ) -> Self {
+        Self {
+            spy,
+            team,
+            target,
+            target_owner,
+            placed_tick: tick,
+            removed_tick: None,
+            lifetime: 0.0,
+            target_destroyed: false,
+            removed_by: None,
+            removed_with: None,
+            entity,
+            target_entity,
+        }
+    }
+
+    fn finish(&mut self, tick: DemoTick, interval_per_tick: f32) {
+        let ticks = u32::from(tick).saturating_sub(u32::from(self.placed_tick));
+        self.lifetime = ticks as f32 * interval_per_tick;
+    }
+
+    // Returns whether someone actually took it off. Sappers go down with their building, which
+    // isn't anyone removing them.
+    fn handle_removed_by(&mut self, remover: Option<String>, weapon: &str) -> bool {
+        if self.target_destroyed || remover.as_ref() == Some(&self.spy) {
+            return false;
+        }
+        self.removed_by = remover;
+        self.removed_with = Some(weapon.to_string());
+        true
+    }
+
+    fn handle_target_destroyed(&mut self) {
+        self.target_destroyed = true;
+        self.removed_by = None;
+        self.removed_with = None;
+    }
+}
+
+/// Sappers that are still on something, keyed by entity.
+#[derive(Debug, Default)]
+pub struct SapperTracker {
+    live: HashMap<EntityId, SapperRecord>,
+}
+
+impl SapperTracker {
+    pub fn handle_placed(&mut self, record: SapperRecord) {
+        self.live.insert(record.entity, record);
+    }
+
+    // Returns none if the sapper isn't being tracked, otherwise whether someone took it off.
+    pub fn handle_sapper_destroyed(
+        &mut self,
+        entity: EntityId,
+        remover: Option<String>,
+        weapon: &str,
+    ) -> Option<bool> {
+        let record = self.live.get_mut(&entity)?;
+        Some(record.handle_removed_by(remover, weapon))
+    }
+
+    // Returns the spies whose sappers were on it.
+    pub fn handle_target_destroyed(&mut self, target: EntityId) -> Vec<String> {
+        let mut spies = vec![];
+        for record in self.live.values_mut() {
+            if record.target_entity == target {
+                record.handle_target_destroyed();
+                spies.push(record.spy.clone());
+            }
+        }
+        spies
+    }
+
+    pub fn handle_removed(
+        &mut self,
+        entity: EntityId,
+        tick: DemoTick,
+        interval_per_tick: f32,
+    ) -> Option<SapperRecord> {
+        let mut record = self.live.remove(&entity)?;
+        record.removed_tick = Some(tick);
+        record.finish(tick, interval_per_tick);
+        Some(record)
+    }
+
+    // Hands back every sapper still on something, which stop being tracked.
+    pub fn take_standing(&mut self, tick: DemoTick, interval_per_tick: f32) -> Vec<SapperRecord> {
+        let mut standing: Vec<_> = self.live.drain().map(|(_, s)| s).collect();
+        for record in &mut standing {
+            record.finish(tick, interval_per_tick);
+        }
+        standing.sort_by_key(|s| s.placed_tick);
+        standing
+    }
+}
+
+// As with buildings, the events for sappers coming off can arrive after their entity is gone.
+// Returns whether someone took it off.
+pub fn handle_late_sapper_removal(
+    records: &mut [SapperRecord],
+    entity: EntityId,
+    tick: DemoTick,
+    remover: Option<String>,
+    weapon: &str,
+) -> bool {
+    records
+        .iter_mut()
+        .rev()
+        .find(|s| s.entity == entity && s.removed_tick == Some(tick))
+        .is_some_and(|record| record.handle_removed_by(remover, weapon))
+}
+
+// Returns the spies whose sappers were on it.
+pub fn handle_late_target_destruction(
+    records: &mut [SapperRecord],
+    target: EntityId,
+    tick: DemoTick,
+) -> Vec<String> {
+    let mut spies = vec![];
+    for record in records
+        .iter_mut()
+        .filter(|s| s.target_entity == target && s.removed_tick == Some(tick))
+    {
+        record.handle_target_destroyed();
+        spies.push(record.spy.clone());
+    }
+    spies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::region::{Region, RegionShape};
+
+    const INTERVAL: f32 = 0.5;
+
+    fn tick(t: u32) -> DemoTick {
+        DemoTick::from(t)
+    }
+
+    fn state(kind: BuildingKind, level: u32, carried: bool, shielded: bool) -> BuildingState {
+        BuildingState {
+            kind,
+            level,
+            mini: false,
+            carried,
+            shielded,
+            origin: Vec3::new(level as f32, 0.0, 0.0),
+        }
+    }
+
+    fn build(tracker: &mut BuildingTracker, id: u32, kind: BuildingKind, at: u32) {
+        let state = state(kind, 1, false, false);
+        let id = EntityId::from(id);
+        let record = BuildingRecord::new(id, "engy".into(), Team::Red, tick(at), &state, None);
+        tracker.handle_built(record);
+    }
+
+    const ROCKET: SentryFire = SentryFire {
+        wrangled: true,
+        rocket: true,
+    };
+
+    #[test]
+    fn records_a_buildings_life() {
+        let mut tracker = BuildingTracker::default();
+        let id = EntityId::from(10u32);
+        build(&mut tracker, 10, BuildingKind::Sentry, 100);
+        let deck = RegionMap::new(vec![Region {
+            name: "deck".into(),
+            shape: RegionShape::Box {
+                min: [2.0, -10.0, -10.0],
+                max: [10.0, 10.0, 10.0],
+            },
+        }]);
+
+        for (t, level, carried, shielded) in [
+            (110, 2, false, false),
+            (120, 2, false, true),
+            (130, 2, false, false),
+            (140, 2, true, false),
+            (150, 3, false, true),
+        ] {
+            let state = state(BuildingKind::Sentry, level, carried, shielded);
+            tracker.handle_update(id, tick(t), &state, INTERVAL, Some(&deck));
+        }
+
+        assert!(tracker.handle_destroyed(id, tick(160), Some("spy".into()), "knife"));
+        let record = tracker.handle_removed(id, tick(160), INTERVAL).unwrap();
+        assert!(tracker.handle_removed(id, tick(161), INTERVAL).is_none());
+
+        let levels: Vec<_> = record.levels.iter().map(|l| l.level).collect();
+        assert_eq!(levels, [1, 2, 3]);
+        assert_eq!(record.times_carried, 1);
+        assert_eq!(record.redeploys.len(), 1);
+        assert_eq!(record.redeploys[0].tick, tick(150));
+        assert_eq!(record.redeploys[0].region.as_deref(), Some("deck"));
+        assert_eq!(record.shielded_time, (10 + 10) as f32 * INTERVAL);
+        assert_eq!(record.lifetime, 60.0 * INTERVAL);
+        assert_eq!(record.removed_tick, Some(tick(160)));
+        assert_eq!(record.destroyed_by.as_deref(), Some("spy"));
+        assert_eq!(record.destroyed_with.as_deref(), Some("knife"));
+    }
+
+    #[test]
+    fn destruction_after_the_entity_is_gone() {
+        let mut tracker = BuildingTracker::default();
+        let id = EntityId::from(10u32);
+        build(&mut tracker, 10, BuildingKind::Dispenser, 100);
+        let mut records = vec![tracker.handle_removed(id, tick(200), INTERVAL).unwrap()];
+
+        assert!(!tracker.handle_destroyed(id, tick(200), Some("soldier".into()), "rocket"));
+        handle_late_destruction(&mut records, id, tick(201), None, "late");
+        assert_eq!(records[0].destroyed_tick, None);
+        let other = EntityId::from(11u32);
+        handle_late_destruction(&mut records, other, tick(200), None, "other");
+        assert_eq!(records[0].destroyed_tick, None);
+
+        let soldier = Some("soldier".to_string());
+        handle_late_destruction(&mut records, id, tick(200), soldier, "rocket");
+        assert_eq!(records[0].destroyed_tick, Some(tick(200)));
+        assert_eq!(records[0].destroyed_by.as_deref(), Some("soldier"));
+    }
+
+    #[test]
+    fn credits_the_sentry_that_fired() {
+        let [first, second, dispenser] = [10u32, 11, 12].map(EntityId::from);
+        let mut tracker = BuildingTracker::default();
+        build(&mut tracker, 10, BuildingKind::Sentry, 100);
+        build(&mut tracker, 11, BuildingKind::Sentry, 100);
+        build(&mut tracker, 12, BuildingKind::Dispenser, 100);
+        let mut removed = vec![];
+
+        let sentry = tracker.sentry(&mut removed, second, tick(150));
+        sentry.unwrap().handle_sentry_damage(40, ROCKET);
+        assert!(tracker.sentry(&mut removed, dispenser, tick(150)).is_none());
+
+        // A sentry's last rocket can land after it's gone, on the tick it went.
+        let record = tracker.handle_removed(second, tick(160), INTERVAL);
+        removed.extend(record);
+        let gone = tracker.sentry(&mut removed, second, tick(160));
+        gone.unwrap().handle_sentry_kill(ROCKET);
+        assert!(tracker.sentry(&mut removed, second, tick(161)).is_none());
+
+        let standing = tracker.take_standing(tick(200), INTERVAL);
+        let untouched = standing.iter().find(|b| b.entity == first).unwrap();
+        assert_eq!((untouched.damage, untouched.kills), (0, 0));
+        let r = &removed[0];
+        assert_eq!((r.damage, r.wrangled_damage, r.rocket_damage), (40, 40, 40));
+        assert_eq!((r.kills, r.wrangled_kills, r.rocket_kills), (1, 1, 1));
+    }
+
+    #[test]
+    fn hands_back_standing_buildings_in_order() {
+        let mut tracker = BuildingTracker::default();
+        build(&mut tracker, 10, BuildingKind::Teleporter, 300);
+        build(&mut tracker, 11, BuildingKind::Sentry, 100);
+        build(&mut tracker, 12, BuildingKind::Dispenser, 200);
+
+        let standing = tracker.take_standing(tick(400), INTERVAL);
+        let built: Vec<_> = standing.iter().map(|b| u32::from(b.built_tick)).collect();
+        assert_eq!(built, [100, 200, 300]);
+        assert_eq!(standing[0].lifetime, 300.0 * INTERVAL);
+        assert!(standing.iter().all(|b| b.removed_tick.is_none()));
+        assert!(tracker.take_standing(tick(500), INTERVAL).is_empty());
+    }
+
+    fn sapper(tracker: &mut SapperTracker, id: u32, target: u32, at: u32) {
+        let (id, target) = (EntityId::from(id), EntityId::from(target));
+        let on = SapTarget::Building(BuildingKind::Sentry);
+        let owner = Some("engy".to_string());
+        let record = SapperRecord::new(id, "spy".into(), Team::Blue, on, target, owner, tick(at));
+        tracker.handle_placed(record);
+    }
+
+    #[test]
+    fn sappers_taken_off() {
+        let mut tracker = SapperTracker::default();
+        let id = EntityId::from(20u32);
+        sapper(&mut tracker, 20, 10, 100);
+
+        let unknown = EntityId::from(21u32);
+        assert_eq!(
+            tracker.handle_sapper_destroyed(unknown, None, "wrench"),
+            None
+        );
+        let engy = Some("engy".to_string());
+        assert_eq!(
+            tracker.handle_sapper_destroyed(id, engy, "wrench"),
+            Some(true)
+        );
+
+        let record = tracker.handle_removed(id, tick(110), INTERVAL).unwrap();
+        assert_eq!(record.removed_by.as_deref(), Some("engy"));
+        assert_eq!(record.removed_with.as_deref(), Some("wrench"));
+        assert_eq!(record.lifetime, 10.0 * INTERVAL);
+        assert!(!record.target_destroyed);
+    }
+
+    #[test]
+    fn sappers_on_destroyed_buildings_arent_removed() {
+        let mut tracker = SapperTracker::default();
+        let id = EntityId::from(20u32);
+        sapper(&mut tracker, 20, 10, 100);
+        sapper(&mut tracker, 22, 11, 100);
+
+        assert_eq!(
+            tracker.handle_target_destroyed(EntityId::from(10u32)),
+            ["spy"]
+        );
+        // The sapper's own destruction comes after, and isn't anyone taking it off.
+        let engy = Some("engy".to_string());
+        assert_eq!(
+            tracker.handle_sapper_destroyed(id, engy, "wrench"),
+            Some(false)
+        );
+        // Nor is the spy taking back their own sapper.
+        let spy = Some("spy".to_string());
+        let other = EntityId::from(22u32);
+        assert_eq!(
+            tracker.handle_sapper_destroyed(other, spy, "sapper"),
+            Some(false)
+        );
+
+        let record = tracker.handle_removed(id, tick(110), INTERVAL).unwrap();
+        assert!(record.target_destroyed);
+        assert_eq!(record.removed_by, None);
+        let standing = tracker.take_standing(tick(120), INTERVAL);
+        assert_eq!(standing.len(), 1);
+        assert_eq!(standing[0].removed_tick, None);
+        assert_eq!(standing[0].lifetime, 20.0 * INTERVAL);
+    }
+
+    #[test]
+    fn sapper_events_after_the_entity_is_gone() {
+        let mut tracker = SapperTracker::default();
+        let (id, target) = (EntityId::from(20u32), EntityId::from(10u32));
+        sapper(&mut tracker, 20, 10, 100);
+        let mut records = vec![tracker.handle_removed(id, tick(110), INTERVAL).unwrap()];
+        assert!(tracker.handle_target_destroyed(target).is_empty());
+
+        let engy = || Some("engy".to_string());
+        assert!(!handle_late_sapper_removal(
+            &mut records,
+            id,
+            tick(111),
+            engy(),
+            "wrench"
+        ));
+        assert!(handle_late_sapper_removal(
+            &mut records,
+            id,
+            tick(110),
+            engy(),
+            "wrench"
+        ));
+        assert_eq!(records[0].removed_by.as_deref(), Some("engy"));
+
+        // The building going down later on the same tick overrides the removal.
+        assert!(handle_late_target_destruction(&mut records, target, tick(111)).is_empty());
+        assert_eq!(
+            handle_late_target_destruction(&mut records, target, tick(110)),
+            ["spy"]
+        );
+        assert!(records[0].target_destroyed);
+        assert_eq!(records[0].removed_by, None);
+    }
+}
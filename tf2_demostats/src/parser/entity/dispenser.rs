@@ -1,7 +1,7 @@
 use crate::{
     convert_vec,
     parser::{
-        entity::{Entity, EntityClass, SENTRY_BOX},
+        entity::{DISPENSER_HULL, Entity, EntityClass},
         props::*,
         summarizer::MatchAnalyzerView,
     },
@@ -25,6 +25,7 @@ pub struct Dispenser {
     pub owner: u32, // handle id
     pub owner_entity: EntityId,
     pub level: u32,
+    pub carried: bool,
 }
 
 impl Dispenser {
@@ -46,6 +47,7 @@ impl Dispenser {
                     }
                 }
                 (UPGRADE_LEVEL, &SendPropValue::Integer(l)) => patch.level = Some(l as u32),
+                (OBJECT_CARRIED, &SendPropValue::Integer(c)) => patch.carried = Some(c != 0),
                 _ => {}
             }
         }
@@ -82,6 +84,7 @@ impl Entity for Dispenser {
                 error!("No level for Dispenser gun! {packet:?}");
                 0
             }),
+            carried: patch.carried.unwrap_or_default(),
         }
     }
 
@@ -100,7 +103,7 @@ impl Entity for Dispenser {
     }
 
     fn shape(&self) -> Option<SharedShape> {
-        Some(SENTRY_BOX.clone())
+        Some(DISPENSER_HULL.clone())
     }
     fn origin(&self) -> Option<Vec3> {
         Some(self.origin)
@@ -113,4 +116,7 @@ impl Entity for Dispenser {
     fn class(&self) -> EntityClass {
         EntityClass::Dispenser
     }
+    fn dispenser(&self) -> Option<&Dispenser> {
+        Some(self)
+    }
 }
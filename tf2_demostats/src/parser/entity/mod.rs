@@ -1,9 +1,6 @@
 use crate::{parser::summarizer::MatchAnalyzerView, Vec3};
 use optfield::optfield;
-use parry3d::{
-    math::Vector,
-    shape::{Cuboid, SharedShape},
-};
+use parry3d::{math::Isometry, shape::SharedShape};
 use std::any::Any;
 use tf_demo_parser::{demo::message::packetentities::PacketEntity, ParserState};
 
@@ -16,6 +13,9 @@ pub use dispenser::*;
 pub mod teleporter;
 pub use teleporter::*;
 
+pub mod sapper;
+pub use sapper::*;
+
 pub mod projectile;
 pub use projectile::*;
 
@@ -34,6 +34,7 @@ pub enum EntityClass {
     Sentry,
     Dispenser,
     Teleporter,
+    Sapper,
     Weapon,
     Shield,
     Player,
@@ -99,6 +100,15 @@ pub trait Entity: std::fmt::Debug {
     fn sentry(&self) -> Option<&Sentry> {
         None
     }
+    fn dispenser(&self) -> Option<&Dispenser> {
+        None
+    }
+    fn teleporter(&self) -> Option<&Teleporter> {
+        None
+    }
+    fn sapper(&self) -> Option<&Sapper> {
+        None
+    }
     fn shield(&self) -> Option<&Shield> {
         None
     }
@@ -139,10 +149,77 @@ impl Entity for Unknown {
     }
 }
 
+// A box from `mins` to `maxs` relative to an entity's origin, the way the game sizes hulls.
+fn hull(mins: [f32; 3], maxs: [f32; 3]) -> SharedShape {
+    let centre = Isometry::translation(
+        (mins[0] + maxs[0]) / 2.0,
+        (mins[1] + maxs[1]) / 2.0,
+        (mins[2] + maxs[2]) / 2.0,
+    );
+    let cuboid = SharedShape::cuboid(
+        (maxs[0] - mins[0]) / 2.0,
+        (maxs[1] - mins[1]) / 2.0,
+        (maxs[2] - mins[2]) / 2.0,
+    );
+    SharedShape::compound(vec![(centre, cuboid)])
+}
+
+// Collision shapes must be statics, see the note on Arc::ptr_eq in MatchAnalyzer.
 lazy_static::lazy_static! {
-    // TODO: real valuess, switch by sentry level
-    static ref SENTRY_BOX: SharedShape = SharedShape::new(Cuboid::new(Vector::new(49.0, 49.0, 83.0)));
+    pub static ref PLAYER_HULL: SharedShape = hull([-24.0, -24.0, 0.0], [24.0, 24.0, 82.0]);
+    pub static ref PLAYER_DUCKED_HULL: SharedShape = hull([-24.0, -24.0, 0.0], [24.0, 24.0, 62.0]);
+
+    // The game keeps the same hull at every level; mini sentries are scaled down to 0.75.
+    static ref SENTRY_HULL: SharedShape = hull([-20.0, -20.0, 0.0], [20.0, 20.0, 66.0]);
+    static ref MINI_SENTRY_HULL: SharedShape = hull([-15.0, -15.0, 0.0], [15.0, 15.0, 49.5]);
+    static ref DISPENSER_HULL: SharedShape = hull([-20.0, -20.0, 0.0], [20.0, 20.0, 55.0]);
+    static ref TELEPORTER_HULL: SharedShape = hull([-24.0, -24.0, 0.0], [24.0, 24.0, 12.0]);
+
+    // Rockets are traced as points; give them a sliver of volume so queries still find them.
+    static ref ROCKET_HULL: SharedShape = hull([-0.5, -0.5, -0.5], [0.5, 0.5, 0.5]);
+    static ref ARROW_HULL: SharedShape = hull([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]);
+    static ref GRENADE_HULL: SharedShape = hull([-2.0, -2.0, -2.0], [2.0, 2.0, 2.0]);
+    static ref THROWN_HULL: SharedShape = hull([-4.0, -4.0, -4.0], [4.0, 4.0, 4.0]);
+    static ref PROJECTILE_HULL: SharedShape = hull([-10.0, -10.0, -10.0], [10.0, 10.0, 10.0]);
+}
 
-    // TODO: real valuess, switch by projectile type
-    static ref PROJECTILE_BOX: SharedShape = SharedShape::new(Cuboid::new(Vector::new(10.0, 10.0, 10.0)));
+pub fn player_hull(ducked: bool) -> &'static SharedShape {
+    if ducked {
+        &PLAYER_DUCKED_HULL
+    } else {
+        &PLAYER_HULL
+    }
+}
+
+fn projectile_hull(kind: ProjectileType) -> &'static SharedShape {
+    match kind {
+        ProjectileType::Rocket | ProjectileType::SentryRocket | ProjectileType::CowMangler => {
+            &ROCKET_HULL
+        }
+        ProjectileType::HuntsmanArrow
+        | ProjectileType::HealingBolt
+        | ProjectileType::RescueRanger
+        | ProjectileType::Flare
+        | ProjectileType::DetonatorFlare
+        | ProjectileType::ManmelterFlare
+        | ProjectileType::ScorchShotFlare => &ARROW_HULL,
+        ProjectileType::Pipe
+        | ProjectileType::LochNLoad
+        | ProjectileType::IronBomber
+        | ProjectileType::LooseCannon
+        | ProjectileType::StickyBomb
+        | ProjectileType::StickyBombJumper
+        | ProjectileType::QuickieBomb
+        | ProjectileType::ScottishResistance => &GRENADE_HULL,
+        ProjectileType::Jarate
+        | ProjectileType::MadMilk
+        | ProjectileType::GasPasser
+        | ProjectileType::Cleaver
+        | ProjectileType::Sandman
+        | ProjectileType::WrapAssassin => &THROWN_HULL,
+        ProjectileType::EnergyRing
+        | ProjectileType::ShortCircuit
+        | ProjectileType::DragonsFuryFire
+        | ProjectileType::Unknown => &PROJECTILE_HULL,
+    }
 }
@@ -1,13 +1,14 @@
 use crate::{
     parser::{
-        entity::{Entity, EntityClass},
+        entity::{player_hull, Entity, EntityClass},
         game::{update_condition, Flags, PlayerCondition, INVALID_HANDLE},
         props::*,
-        summarizer::MatchAnalyzerView,
+        summarizer::{Event, MatchAnalyzerView},
     },
     Vec2, Vec3,
 };
 use enumset::EnumSet;
+use parry3d::shape::SharedShape;
 use std::any::Any;
 use tf_demo_parser::{
     demo::{
@@ -21,6 +22,14 @@ use tf_demo_parser::{
 };
 use tracing::{error, trace};
 
+// Terminal velocity in units per second. Anything faster was a respawn or teleport.
+const MAX_SPEED: f32 = 3500.0;
+
+// Whether covering `distance` in `seconds` is too fast to have been movement.
+fn warped(distance: f32, seconds: f32) -> bool {
+    distance / seconds > MAX_SPEED
+}
+
 //#[optfield::optfield(PlayerPatch, merge_fn, attrs)]
 #[derive(Debug, PartialEq, Default)]
 pub struct Player {
@@ -51,10 +60,13 @@ pub struct Player {
     pub scoreboard_damage: u32,
     pub on_ground: bool,
     pub in_water: bool,
+    pub ducked: bool,
     pub started_flying: DemoTick,
 
     pub sim_time: u32,
     pub origin: Vec3,
+    pub velocity: Vec3, // computed, units per tick
+    pub origin_tick: DemoTick,
     pub eye: Vec2,
     pub condition: EnumSet<PlayerCondition>,
     pub condition_source: u32,
@@ -69,6 +81,7 @@ pub struct Player {
 
 #[derive(Default)]
 struct PlayerPatch {
+    tick: Option<DemoTick>,
     scoreboard_kills: Option<u32>,
     scoreboard_assists: Option<u32>,
     scoreboard_deaths: Option<u32>,
@@ -208,6 +221,23 @@ impl Player {
         }
     }
 
+    fn patched_condition(&self, patch: &PlayerPatch) -> EnumSet<PlayerCondition> {
+        let mut condition = self.condition;
+        if let Some(bits) = patch.condition_bits[0] {
+            update_condition::<0>(&mut condition, bits);
+        }
+        if let Some(bits) = patch.condition_bits[1] {
+            update_condition::<32>(&mut condition, bits);
+        }
+        if let Some(bits) = patch.condition_bits[2] {
+            update_condition::<64>(&mut condition, bits);
+        }
+        if let Some(bits) = patch.condition_bits[3] {
+            update_condition::<96>(&mut condition, bits);
+        }
+        condition
+    }
+
     fn apply_patch(&mut self, patch: &PlayerPatch) {
         self.handle = patch.handle.unwrap_or(self.handle);
         self.health = patch.health.unwrap_or(self.health);
@@ -216,6 +246,7 @@ impl Player {
         self.class = patch.class.unwrap_or(self.class);
         self.team = patch.team.unwrap_or(self.team);
 
+        let previous_origin = self.origin;
         if let Some(xy) = patch.origin_xy {
             self.origin.x = xy.x;
             self.origin.y = xy.y;
@@ -224,6 +255,20 @@ impl Player {
             self.origin.z = z;
         }
 
+        if let Some(tick) = patch.tick
+            && (patch.origin_xy.is_some() || patch.origin_z.is_some())
+        {
+            if tick > self.origin_tick && self.origin_tick != DemoTick::default() {
+                let elapsed = u32::from(tick) - u32::from(self.origin_tick);
+                self.velocity = ((self.origin - previous_origin) / elapsed as f32).into();
+            }
+            self.origin_tick = tick;
+        }
+
+        if let Some(flags) = patch.flags {
+            self.ducked = flags.contains(Flags::Ducking);
+        }
+
         if let Some(x) = patch.eye_x {
             self.eye.x = x;
         }
@@ -231,18 +276,7 @@ impl Player {
             self.eye.y = y;
         }
 
-        if let Some(bits) = patch.condition_bits[0] {
-            update_condition::<0>(&mut self.condition, bits);
-        }
-        if let Some(bits) = patch.condition_bits[1] {
-            update_condition::<32>(&mut self.condition, bits);
-        }
-        if let Some(bits) = patch.condition_bits[2] {
-            update_condition::<64>(&mut self.condition, bits);
-        }
-        if let Some(bits) = patch.condition_bits[3] {
-            update_condition::<96>(&mut self.condition, bits);
-        }
+        self.condition = self.patched_condition(patch);
 
         if let Some(aw) = patch.active_weapon_handle {
             self.active_weapon_handle = aw;
@@ -271,7 +305,10 @@ impl Entity for Player {
         parser_state: &ParserState,
         game: &mut MatchAnalyzerView,
     ) -> Self {
-        let mut patch = PlayerPatch::default();
+        let mut patch = PlayerPatch {
+            tick: Some(game.tick),
+            ..Default::default()
+        };
         Player::parse(packet, parser_state, &mut patch);
 
         let mut s = Self::default();
@@ -283,6 +320,9 @@ impl Entity for Player {
                 if let Some(summary) = game.player_summaries.get_mut(&steamid) {
                     summary.class = patch.class.unwrap_or(summary.class);
                     summary.health = patch.health.unwrap_or(summary.health);
+                    if let Some(health) = patch.health {
+                        summary.damage_log.record_health(game.tick, health);
+                    }
 
                     for &w in patch.weapon_handles.iter() {
                         if let Some(w) = w {
@@ -320,7 +360,10 @@ impl Entity for Player {
     ) -> Box<dyn Any> {
         let user_id = self.user_id;
 
-        let mut patch = Box::new(PlayerPatch::default());
+        let mut patch = Box::new(PlayerPatch {
+            tick: Some(game.tick),
+            ..Default::default()
+        });
         Player::parse(packet, parser_state, &mut patch);
 
         let Some(steamid) = game.user_id_to_steam_id.get(&user_id).cloned() else {
@@ -349,6 +392,16 @@ impl Entity for Player {
             summary.scoreboard_deaths = Some(deaths);
         }
 
+        // Account for the time since the last update before any state changes.
+        let interval = parser_state.demo_meta.interval_per_tick;
+        let alive = self.health > 0;
+        let last = summary.movement_tick;
+        if alive && game.tick > last && last != DemoTick::default() {
+            let seconds = (u32::from(game.tick) - u32::from(last)) as f32 * interval;
+            summary.handle_movement_time(seconds, self.ducked);
+        }
+        summary.movement_tick = game.tick;
+
         if let Some(flags) = patch.flags {
             let was_in_air = summary.in_air();
             summary.on_ground = flags.contains(Flags::OnGround);
@@ -359,6 +412,23 @@ impl Entity for Player {
             }
         }
 
+        let condition = self.patched_condition(&patch);
+        if condition.contains(PlayerCondition::Burning)
+            && !self.condition.contains(PlayerCondition::Burning)
+        {
+            summary.ignited = game.tick;
+        }
+        if condition.contains(PlayerCondition::Bleeding)
+            && !self.condition.contains(PlayerCondition::Bleeding)
+        {
+            summary.started_bleeding = game.tick;
+        }
+        if condition.contains(PlayerCondition::Teleported)
+            && !self.condition.contains(PlayerCondition::Teleported)
+        {
+            game.tick_events.push(Event::Teleported(user_id));
+        }
+
         if let Some(xy) = patch.origin_xy {
             summary.origin.x = xy.x;
             summary.origin.y = xy.y;
@@ -366,6 +436,28 @@ impl Entity for Player {
         if let Some(z) = patch.origin_z {
             summary.origin.z = z;
         }
+        if !summary.in_air() {
+            summary.grounded_z = summary.origin.z;
+        }
+
+        if alive
+            && (patch.origin_xy.is_some() || patch.origin_z.is_some())
+            && game.tick > self.origin_tick
+            && self.origin_tick != DemoTick::default()
+        {
+            let seconds = (u32::from(game.tick) - u32::from(self.origin_tick)) as f32 * interval;
+            let distance = (summary.origin - self.origin).norm();
+            if !warped(distance, seconds) {
+                summary.handle_movement(distance, seconds);
+            }
+        }
+
+        summary.handle_blast_jump_condition(
+            game.tick,
+            self.condition.contains(PlayerCondition::BlastJumping),
+            condition.contains(PlayerCondition::BlastJumping),
+            interval,
+        );
 
         if let Some(aw) = patch.active_weapon_handle {
             game.weapon_owners.insert(aw, user_id);
@@ -385,6 +477,9 @@ impl Entity for Player {
 
         summary.class = patch.class.unwrap_or(summary.class);
         summary.health = patch.health.unwrap_or(summary.health);
+        if let Some(health) = patch.health {
+            summary.damage_log.record_health(game.tick, health);
+        }
 
         patch
     }
@@ -422,6 +517,10 @@ impl Entity for Player {
         EntityClass::Player
     }
 
+    fn shape(&self) -> Option<SharedShape> {
+        Some(player_hull(self.ducked).clone())
+    }
+
     fn origin(&self) -> Option<Vec3> {
         Some(self.origin)
     }
@@ -430,3 +529,17 @@ impl Entity for Player {
         Some(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_faster_than_max_speed_are_warps() {
+        assert!(!warped(MAX_SPEED / 66.0, 1.0 / 66.0));
+        assert!(!warped(0.0, 1.0 / 66.0));
+        assert!(warped(MAX_SPEED / 66.0 + 1.0, 1.0 / 66.0));
+        // A respawn across the map in a single tick.
+        assert!(warped(4000.0, 0.015));
+    }
+}
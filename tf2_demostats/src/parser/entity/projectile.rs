@@ -1,38 +1,54 @@
 use crate::{
     Vec3, convert_vec,
     parser::{
-        entity::{Entity, EntityClass, PROJECTILE_BOX},
+        entity::{Entity, EntityClass, projectile_hull},
         game::{Effects, GrenadeType, INVALID_HANDLE},
         props::*,
-        summarizer::{Explosion, MatchAnalyzerView},
+        summarizer::{ENTITY_GROUP, Explosion, MatchAnalyzerView},
         weapon::projectile_explosion_radius,
     },
     schema::{Attribute, StringAttribute},
 };
 use enumset::EnumSet;
-use parry3d::{math::Vector, shape::SharedShape};
-use rapier3d::prelude::{Aabb, Ball, BoundingVolume, Cuboid, QueryFilter};
+use parry3d::{
+    math::{Isometry, Vector},
+    shape::SharedShape,
+};
+use rapier3d::prelude::{
+    Aabb, Ball, BoundingVolume, Cuboid, Group, InteractionGroups, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
 use std::any::Any;
 use tf_demo_parser::{
     ParserState,
     demo::{
-        message::packetentities::PacketEntity, packet::datatable::ClassId, parser::analyser::Team,
+        message::packetentities::{EntityId, PacketEntity},
+        packet::datatable::ClassId,
+        parser::analyser::Team,
         sendprop::SendPropValue,
     },
 };
 use tracing::error;
 
+// How far outside its sentry's hull a rocket can be when first seen. The launcher sits on top of
+// a level 3 sentry, past the edge of the hull, and a rocket can already have flown for a tick by
+// then (about 17 units at 1100 units/s and 66 ticks/s).
+const SENTRY_LAUNCHER_OFFSET: f32 = 32.0;
+
 #[optfield::optfield(ProjectilePatch, merge_fn, attrs)]
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct Projectile {
+    pub id: EntityId,
     pub original_launcher_handle: u32,
     pub launcher_schema_id: Option<u32>,
     pub is_sentry: bool,
+    pub sentry: Option<EntityId>, // that fired it, for sentry rockets
     pub origin: Vec3,
     pub velocity: Vec3, // computed
     pub original_owner: u32,
     pub owner: u32,
     pub is_reflected: bool,
+    pub times_reflected: u32,
     pub original_team: Team,
     pub team: Team,
     pub class_name: String,
@@ -42,7 +58,9 @@ pub struct Projectile {
     pub effects: EnumSet<Effects>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
 pub enum ProjectileType {
     EnergyRing, // Pomson, Bison
     HealingBolt,
@@ -121,7 +139,8 @@ impl Projectile {
                     patch.origin = Some(convert_vec(o));
                 }
                 (ROCKET_DEFLECTED | GRENADE_DEFLECTED, &SendPropValue::Integer(b)) => {
-                    patch.is_reflected = Some(b > 0)
+                    patch.is_reflected = Some(b > 0);
+                    patch.times_reflected = Some(b as u32);
                 }
                 (OWNER | DEFLECT_OWNER, &SendPropValue::Integer(h)) => {
                     let h = h as u32;
@@ -241,40 +260,42 @@ impl Entity for Projectile {
             Vec3::default()
         });
 
-        let is_sentry = if class_name == "CTFProjectile_SentryRocket" {
-            game.world.intersections_with_point(
+        // Rockets leave from the launcher on top of a level 3 sentry, outside its hull, so the
+        // sentry is looked for in a bigger box around it.
+        let is_sentry = class_name == "CTFProjectile_SentryRocket";
+        let mut sentry = None;
+        if is_sentry {
+            let reach = Cuboid::new(Vector::repeat(SENTRY_LAUNCHER_OFFSET));
+            let mut nearest: Option<(usize, f32)> = None;
+            game.world.intersections_with_shape(
                 game.rigid_body_set,
                 game.collider_set,
-                &origin,
-                QueryFilter::new().predicate(&|_handle, c| {
-                    if let Some(e) = &game.entities[c.user_data as usize] {
-                        e.class() == EntityClass::Sentry
-                    } else {
-                        error!("Could not find entity from user_data: {}", c.user_data);
-                        false
-                    }
-                }),
-                |coll| {
+                &Isometry::translation(origin.x, origin.y, origin.z),
+                &reach,
+                QueryFilter::new()
+                    .groups(InteractionGroups::new(Group::ALL, ENTITY_GROUP))
+                    .predicate(&|_handle, c| {
+                        game.entities[c.user_data as usize]
+                            .as_ref()
+                            .is_some_and(|e| e.class() == EntityClass::Sentry)
+                    }),
+                |handle| {
                     // unwrap() safety: this is a lookup into the very set we are iterating over.
-                    let collider = game.collider_set.get(coll).unwrap();
-
-                    let eid = collider.user_data as usize;
-                    let Some(entity) = &game.entities[eid] else {
-                        error!(
-                            "Collided with a broken entity id when checking sentry rocket {eid}"
-                        );
-                        return false;
-                    };
-
-                    p.owner = entity.owner();
-
-                    false // Only ever look at the first match.
+                    let collider = game.collider_set.get(handle).unwrap();
+                    let distance = (collider.translation() - origin.coords).norm_squared();
+                    if nearest.is_none_or(|(_, d)| distance < d) {
+                        nearest = Some((collider.user_data as usize, distance));
+                    }
+                    true // Rockets fired from sentries built close together could match several.
                 },
             );
-            true
-        } else {
-            false
-        };
+            if let Some((eid, _)) = nearest {
+                sentry = Some(EntityId::from(eid as u32));
+                if let Some(owner) = game.entities[eid].as_ref().and_then(|e| e.owner()) {
+                    p.owner = Some(owner);
+                }
+            }
+        }
 
         let owner = p
             .owner
@@ -407,14 +428,17 @@ impl Entity for Projectile {
         }
 
         Self {
+            id: packet.entity_index,
             launcher_schema_id: launcher_schema,
             is_sentry,
+            sentry,
             original_launcher_handle: 0, // only for reading owner
             origin,
             velocity: Default::default(),
             original_owner,
             owner,
             is_reflected: p.is_reflected.unwrap_or(false),
+            times_reflected: p.times_reflected.unwrap_or(0),
             original_team: p.team.unwrap_or(Team::Spectator),
             team: p.team.unwrap_or(Team::Spectator),
             class_name,
@@ -440,6 +464,12 @@ impl Entity for Projectile {
             error!("Projectile changed team without changing owner entity {patch:?}");
         }
 
+        if let Some(times_reflected) = patch.times_reflected
+            && times_reflected > self.times_reflected
+        {
+            game.handle_projectile_reflected(&patch.owner.unwrap_or(self.owner), self.kind);
+        }
+
         let disappeared = if let Some(new_effects) = patch.effects {
             new_effects.contains(Effects::NoDraw) && !self.effects.contains(Effects::NoDraw)
         } else {
@@ -476,7 +506,7 @@ impl Entity for Projectile {
     }
 
     fn shape(&self) -> Option<SharedShape> {
-        Some(PROJECTILE_BOX.clone())
+        Some(projectile_hull(self.kind).clone())
     }
 
     fn origin(&self) -> Option<Vec3> {
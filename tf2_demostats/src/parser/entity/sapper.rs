@@ -0,0 +1,90 @@
+use crate::{
+    Vec3, convert_vec,
+    parser::{
+        entity::{Entity, EntityClass},
+        game::INVALID_HANDLE,
+        props::*,
+        summarizer::MatchAnalyzerView,
+    },
+};
+use std::any::Any;
+use tf_demo_parser::{
+    ParserState,
+    demo::{message::packetentities::PacketEntity, sendprop::SendPropValue},
+};
+use tracing::error;
+
+#[optfield::optfield(SapperPatch, merge_fn, attrs)]
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Sapper {
+    pub origin: Vec3,
+    pub owner: u32,    // handle id
+    pub built_on: u32, // handle id of the building or robot it's on
+}
+
+impl Sapper {
+    fn parse(packet: &PacketEntity, parser_state: &ParserState) -> SapperPatch {
+        let mut patch = SapperPatch::default();
+
+        for prop in packet.props(parser_state) {
+            match (prop.identifier, &prop.value) {
+                (ORIGIN, &SendPropValue::Vector(o)) => patch.origin = Some(convert_vec(o)),
+                (BUILDER, &SendPropValue::Integer(b)) => patch.owner = Some(b as u32),
+                (BUILT_ON, &SendPropValue::Integer(b)) => patch.built_on = Some(b as u32),
+                _ => {}
+            }
+        }
+        patch
+    }
+}
+
+impl Entity for Sapper {
+    fn new(
+        packet: &PacketEntity,
+        parser_state: &ParserState,
+        _game: &mut MatchAnalyzerView,
+    ) -> Self {
+        let patch = Sapper::parse(packet, parser_state);
+
+        Self {
+            origin: patch.origin.unwrap_or_else(|| {
+                error!("No origin for Sapper! {packet:?}");
+                Vec3::default()
+            }),
+            owner: patch.owner.unwrap_or_else(|| {
+                error!("No owner for Sapper! {packet:?}");
+                0
+            }),
+            built_on: patch.built_on.unwrap_or(INVALID_HANDLE),
+        }
+    }
+
+    fn parse_preserve(
+        &self,
+        packet: &PacketEntity,
+        parser_state: &ParserState,
+        _game: &mut MatchAnalyzerView,
+    ) -> Box<dyn Any> {
+        Box::new(Sapper::parse(packet, parser_state))
+    }
+
+    fn apply_preserve(&mut self, patch: Box<dyn Any>) {
+        let patch = patch.downcast::<SapperPatch>().unwrap();
+        self.merge_opt(*patch);
+    }
+
+    fn origin(&self) -> Option<Vec3> {
+        Some(self.origin)
+    }
+
+    fn owner(&self) -> Option<u32> {
+        Some(self.owner)
+    }
+
+    fn class(&self) -> EntityClass {
+        EntityClass::Sapper
+    }
+    fn sapper(&self) -> Option<&Sapper> {
+        Some(self)
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     convert_vec,
     parser::{
-        entity::{Entity, EntityClass, SENTRY_BOX},
+        entity::{Entity, EntityClass, MINI_SENTRY_HULL, SENTRY_HULL},
         props::*,
         summarizer::MatchAnalyzerView,
     },
@@ -25,7 +25,9 @@ pub struct Sentry {
     pub owner: u32, // handle id
     pub owner_entity: EntityId,
     pub level: u32,
+    pub carried: bool,
     pub is_mini: bool,
+    pub shielded: bool, // wrangled
 }
 
 impl Sentry {
@@ -47,9 +49,11 @@ impl Sentry {
                     }
                 }
                 (UPGRADE_LEVEL, &SendPropValue::Integer(l)) => patch.level = Some(l as u32),
+                (OBJECT_CARRIED, &SendPropValue::Integer(c)) => patch.carried = Some(c != 0),
                 (OBJECT_MAX_HEALTH, &SendPropValue::Integer(l)) => {
                     patch.is_mini = Some(l == 100);
                 }
+                (SENTRY_SHIELD_LEVEL, &SendPropValue::Integer(l)) => patch.shielded = Some(l > 0),
                 _ => {}
             }
         }
@@ -86,10 +90,12 @@ impl Entity for Sentry {
                 error!("No level for Sentry gun! {packet:?}");
                 0
             }),
+            carried: patch.carried.unwrap_or_default(),
             is_mini: patch.is_mini.unwrap_or_else(|| {
                 error!("No is_mini based on max hp for Sentry gun! {packet:?}");
                 false
             }),
+            shielded: patch.shielded.unwrap_or_default(),
         }
     }
 
@@ -108,7 +114,11 @@ impl Entity for Sentry {
     }
 
     fn shape(&self) -> Option<SharedShape> {
-        Some(SENTRY_BOX.clone())
+        if self.is_mini {
+            Some(MINI_SENTRY_HULL.clone())
+        } else {
+            Some(SENTRY_HULL.clone())
+        }
     }
     fn origin(&self) -> Option<Vec3> {
         Some(self.origin)
@@ -1,7 +1,7 @@
 use crate::{
     convert_vec,
     parser::{
-        entity::{Entity, EntityClass, SENTRY_BOX},
+        entity::{Entity, EntityClass, TELEPORTER_HULL},
         props::*,
         summarizer::MatchAnalyzerView,
     },
@@ -25,6 +25,8 @@ pub struct Teleporter {
     pub owner: u32, // handle id
     pub owner_entity: EntityId,
     pub level: u32,
+    pub carried: bool,
+    pub exit: bool,
 }
 
 impl Teleporter {
@@ -46,6 +48,8 @@ impl Teleporter {
                     }
                 }
                 (UPGRADE_LEVEL, &SendPropValue::Integer(l)) => patch.level = Some(l as u32),
+                (OBJECT_CARRIED, &SendPropValue::Integer(c)) => patch.carried = Some(c != 0),
+                (OBJECT_MODE, &SendPropValue::Integer(m)) => patch.exit = Some(m == 1),
                 _ => {}
             }
         }
@@ -82,6 +86,8 @@ impl Entity for Teleporter {
                 error!("No level for Teleporter gun! {packet:?}");
                 0
             }),
+            carried: patch.carried.unwrap_or_default(),
+            exit: patch.exit.unwrap_or_default(),
         }
     }
 
@@ -100,7 +106,7 @@ impl Entity for Teleporter {
     }
 
     fn shape(&self) -> Option<SharedShape> {
-        Some(SENTRY_BOX.clone())
+        Some(TELEPORTER_HULL.clone())
     }
     fn origin(&self) -> Option<Vec3> {
         Some(self.origin)
@@ -113,4 +119,7 @@ impl Entity for Teleporter {
     fn class(&self) -> EntityClass {
         EntityClass::Teleporter
     }
+    fn teleporter(&self) -> Option<&Teleporter> {
+        Some(self)
+    }
 }
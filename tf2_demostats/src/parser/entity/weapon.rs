@@ -29,6 +29,9 @@ pub struct Weapon {
     pub model_id: u32,
 
     pub reset_parity: u32,
+
+    // flamethrower
+    pub weapon_state: u32,
 }
 
 impl Weapon {
@@ -51,6 +54,9 @@ impl Weapon {
                 (MODEL, &SendPropValue::Integer(x)) => patch.model_id = Some(x as u32),
                 (WEAPON_OWNER, &SendPropValue::Integer(x)) => patch.owner = Some(x as u32),
                 (RESET_PARITY, &SendPropValue::Integer(x)) => patch.reset_parity = Some(x as u32),
+                (FLAMETHROWER_STATE, &SendPropValue::Integer(x)) => {
+                    patch.weapon_state = Some(x as u32)
+                }
 
                 _ => {
                     trace!(
@@ -63,6 +69,13 @@ impl Weapon {
     }
 }
 
+impl Weapon {
+    // FT_STATE_STARTFIRING and FT_STATE_FIRING; airblasts use FT_STATE_SECONDARY.
+    pub fn is_firing_flames(&self) -> bool {
+        self.class_name == "CTFFlameThrower" && matches!(self.weapon_state, 1 | 2)
+    }
+}
+
 impl Entity for Weapon {
     fn new(
         packet: &PacketEntity,
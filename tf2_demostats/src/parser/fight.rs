@@ -0,0 +1,327 @@
+use crate::{Vec3, parser::is_zero};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use tf_demo_parser::demo::{data::DemoTick, parser::gamestateanalyser::Team};
+
+#[derive(Clone, Debug)]
+pub struct KillRecord {
+    pub tick: DemoTick,
+    pub killer: String, // steamid
+    pub victim: String, // steamid
+    pub victim_team: Team,
+}
+
+/// A player involved in a hit or kill.
+pub struct Combatant<'a> {
+    pub steamid: &'a str,
+    pub team: Team,
+    pub origin: Vec3,
+}
+
+/// What a kill meant for the fight it happened in.
+#[derive(Debug, Default)]
+pub struct KillContext {
+    pub first_pick: bool,
+    pub traded: Option<String>, // steamid of the teammate whose death this kill avenged
+}
+
+/// A cluster of damage, kills and ubers with no long lulls in between.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Fight {
+    pub start_tick: DemoTick,
+    pub end_tick: DemoTick,
+    pub participants: BTreeSet<String>, // steamids
+
+    #[serde(skip_serializing_if = "is_zero")]
+    pub red_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub blue_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub red_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub blue_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub red_ubers: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub blue_ubers: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winner: Option<Team>, // team with the most kills
+    pub centroid: Vec3, // average position of everyone involved in each hit and kill
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>, // containing the centroid
+
+    #[serde(skip)]
+    position_sum: Vec3,
+    #[serde(skip)]
+    positions: u32,
+}
+
+impl Fight {
+    fn new(tick: DemoTick) -> Self {
+        Self {
+            start_tick: tick,
+            end_tick: tick,
+            participants: Default::default(),
+            red_kills: 0,
+            blue_kills: 0,
+            red_damage: 0,
+            blue_damage: 0,
+            red_ubers: 0,
+            blue_ubers: 0,
+            winner: None,
+            centroid: Vec3::origin(),
+            region: None,
+            position_sum: Vec3::origin(),
+            positions: 0,
+        }
+    }
+
+    pub fn kills(&self) -> u32 {
+        self.red_kills + self.blue_kills
+    }
+
+    pub fn damage(&self) -> u32 {
+        self.red_damage + self.blue_damage
+    }
+
+    fn add(&mut self, player: &Combatant) {
+        self.participants.insert(player.steamid.to_string());
+        self.position_sum += player.origin.coords;
+        self.positions += 1;
+    }
+
+    fn finish(&mut self) {
+        if self.positions > 0 {
+            self.centroid = Vec3::from(self.position_sum.coords / self.positions as f32);
+        }
+        self.winner = match self.red_kills.cmp(&self.blue_kills) {
+            std::cmp::Ordering::Greater => Some(Team::Red),
+            std::cmp::Ordering::Less => Some(Team::Blue),
+            std::cmp::Ordering::Equal => None,
+        };
+    }
+}
+
+/// Splits combat into fights separated by lulls, and remembers recent kills so trades can be
+/// spotted.
+#[derive(Debug, Default)]
+pub struct FightTracker {
+    current: Option<Fight>,
+    has_kill: bool,
+    recent_kills: VecDeque<KillRecord>,
+    fights: Vec<Fight>,
+}
+
+impl FightTracker {
+    // The fight `tick` belongs to. More than `lull` ticks since the last action starts a new one.
+    fn fight(&mut self, tick: DemoTick, lull: u32) -> &mut Fight {
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|f| u32::from(tick).saturating_sub(u32::from(f.end_tick)) > lull)
+        {
+            self.close();
+        }
+
+        let fight = self.current.get_or_insert_with(|| Fight::new(tick));
+        fight.end_tick = tick;
+        fight
+    }
+
+    fn close(&mut self) {
+        if let Some(mut fight) = self.current.take() {
+            fight.finish();
+            self.fights.push(fight);
+        }
+        self.has_kill = false;
+    }
+
+    pub fn handle_damage(
+        &mut self,
+        tick: DemoTick,
+        lull: u32,
+        attacker: &Combatant,
+        victim: &Combatant,
+        damage: u32,
+    ) {
+        let fight = self.fight(tick, lull);
+        fight.add(attacker);
+        fight.add(victim);
+        match attacker.team {
+            Team::Red => fight.red_damage += damage,
+            Team::Blue => fight.blue_damage += damage,
+            _ => {}
+        }
+    }
+
+    pub fn handle_uber(&mut self, tick: DemoTick, lull: u32, medic: &str, team: Team) {
+        let fight = self.fight(tick, lull);
+        fight.participants.insert(medic.to_string());
+        match team {
+            Team::Red => fight.red_ubers += 1,
+            Team::Blue => fight.blue_ubers += 1,
+            _ => {}
+        }
+    }
+
+    pub fn handle_kill(
+        &mut self,
+        tick: DemoTick,
+        lull: u32,
+        trade_window: u32,
+        killer: &Combatant,
+        victim: &Combatant,
+    ) -> KillContext {
+        let fight = self.fight(tick, lull);
+        fight.add(killer);
+        fight.add(victim);
+        match killer.team {
+            Team::Red => fight.red_kills += 1,
+            Team::Blue => fight.blue_kills += 1,
+            _ => {}
+        }
+
+        let first_pick = !self.has_kill;
+        self.has_kill = true;
+
+        while self
+            .recent_kills
+            .front()
+            .is_some_and(|k| u32::from(tick).saturating_sub(u32::from(k.tick)) > trade_window)
+        {
+            self.recent_kills.pop_front();
+        }
+
+        // The victim just killed one of the killer's teammates.
+        let traded = self
+            .recent_kills
+            .iter()
+            .position(|k| k.killer == victim.steamid && k.victim_team == killer.team)
+            .and_then(|i| self.recent_kills.remove(i))
+            .map(|k| k.victim);
+
+        self.recent_kills.push_back(KillRecord {
+            tick,
+            killer: killer.steamid.to_string(),
+            victim: victim.steamid.to_string(),
+            victim_team: victim.team,
+        });
+
+        KillContext { first_pick, traded }
+    }
+
+    // Ends the fight in progress and hands back every fight since the last call.
+    pub fn take_fights(&mut self) -> Vec<Fight> {
+        self.close();
+        self.recent_kills.clear();
+        std::mem::take(&mut self.fights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LULL: u32 = 100;
+    const TRADE_WINDOW: u32 = 50;
+
+    fn red(steamid: &str) -> Combatant<'_> {
+        Combatant {
+            steamid,
+            team: Team::Red,
+            origin: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn blue(steamid: &str) -> Combatant<'_> {
+        Combatant {
+            steamid,
+            team: Team::Blue,
+            origin: Vec3::new(100.0, 0.0, 0.0),
+        }
+    }
+
+    fn kill(
+        tracker: &mut FightTracker,
+        t: u32,
+        killer: &Combatant,
+        victim: &Combatant,
+    ) -> KillContext {
+        tracker.handle_kill(DemoTick::from(t), LULL, TRADE_WINDOW, killer, victim)
+    }
+
+    #[test]
+    fn first_kill_of_each_fight_is_the_pick() {
+        let mut tracker = FightTracker::default();
+        tracker.handle_damage(DemoTick::from(10), LULL, &red("r1"), &blue("b1"), 50);
+        assert!(kill(&mut tracker, 20, &red("r1"), &blue("b1")).first_pick);
+        assert!(!kill(&mut tracker, 30, &blue("b2"), &red("r2")).first_pick);
+        // A lull ends the fight, and the next kill opens a new one.
+        assert!(kill(&mut tracker, 200, &blue("b2"), &red("r1")).first_pick);
+
+        let fights = tracker.take_fights();
+        assert_eq!(fights.len(), 2);
+        assert_eq!((fights[0].red_kills, fights[0].blue_kills), (1, 1));
+        assert_eq!(fights[0].red_damage, 50);
+        assert_eq!(fights[0].winner, None);
+        assert_eq!(fights[1].winner, Some(Team::Blue));
+        assert_eq!(fights[0].participants.len(), 4);
+        assert_eq!(fights[0].centroid, Vec3::new(50.0, 0.0, 0.0));
+        assert!(tracker.take_fights().is_empty());
+    }
+
+    #[test]
+    fn trades_avenge_a_teammate() {
+        let mut tracker = FightTracker::default();
+        assert_eq!(kill(&mut tracker, 10, &blue("b1"), &red("r1")).traded, None);
+        // Killing the killer of a teammate trades them, once.
+        let context = kill(&mut tracker, 30, &red("r2"), &blue("b1"));
+        assert_eq!(context.traded.as_deref(), Some("r1"));
+        assert_eq!(kill(&mut tracker, 40, &red("r3"), &blue("b1")).traded, None);
+    }
+
+    #[test]
+    fn trades_need_to_be_quick_and_against_the_killer() {
+        let mut tracker = FightTracker::default();
+        kill(&mut tracker, 10, &blue("b1"), &red("r1"));
+        // Someone else on the killer's team doesn't count.
+        assert_eq!(kill(&mut tracker, 20, &red("r2"), &blue("b2")).traded, None);
+        assert_eq!(kill(&mut tracker, 61, &red("r2"), &blue("b1")).traded, None);
+
+        kill(&mut tracker, 100, &blue("b1"), &red("r3"));
+        assert_eq!(
+            kill(&mut tracker, 150, &red("r4"), &blue("b1"))
+                .traded
+                .as_deref(),
+            Some("r3")
+        );
+        // Kills before the round's fights were taken are forgotten.
+        kill(&mut tracker, 160, &blue("b1"), &red("r4"));
+        tracker.take_fights();
+        assert_eq!(
+            kill(&mut tracker, 170, &red("r5"), &blue("b1")).traded,
+            None
+        );
+    }
+
+    #[test]
+    fn ubers_join_the_fight() {
+        let mut tracker = FightTracker::default();
+        tracker.handle_damage(DemoTick::from(10), LULL, &red("r1"), &blue("b1"), 30);
+        tracker.handle_uber(DemoTick::from(50), LULL, "medic", Team::Red);
+        tracker.handle_uber(DemoTick::from(500), LULL, "medic2", Team::Blue);
+
+        let fights = tracker.take_fights();
+        assert_eq!(fights.len(), 2);
+        assert_eq!(
+            (fights[0].red_ubers, fights[0].end_tick),
+            (1, DemoTick::from(50))
+        );
+        assert!(fights[0].participants.contains("medic"));
+        assert_eq!(
+            (fights[1].blue_ubers, fights[1].kills(), fights[1].damage()),
+            (1, 0, 0)
+        );
+    }
+}
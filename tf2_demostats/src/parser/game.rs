@@ -1,6 +1,7 @@
 use enumset::{EnumSet, EnumSetType};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
+use tf_demo_parser::demo::message::packetentities::EntityId;
 
 #[derive(
     Copy, Clone, Deserialize, Serialize, IntoPrimitive, TryFromPrimitive, PartialEq, Debug, Default,
@@ -23,6 +24,11 @@ pub enum RoundState {
 
 pub const INVALID_HANDLE: u32 = 0x1fffff;
 
+// Handles are an entity index in the low 11 bits, with a serial number above that.
+pub fn handle_entity(handle: u32) -> EntityId {
+    EntityId::from(handle & 0x7ff)
+}
+
 #[derive(
     Copy, Clone, Deserialize, Serialize, IntoPrimitive, TryFromPrimitive, PartialEq, Debug, Default,
 )]
@@ -129,6 +135,22 @@ pub enum DamageType {
     TauntTrickShot = 86,
 }
 
+impl DamageType {
+    // Damage that sets the victim on fire when it lands. Note that direct flamethrower damage and
+    // afterburn both arrive as `Burning`.
+    pub fn is_fire(&self) -> bool {
+        matches!(
+            self,
+            DamageType::Burning
+                | DamageType::BurningFlare
+                | DamageType::BurningArrow
+                | DamageType::FlareExplosion
+                | DamageType::FlarePellet
+                | DamageType::DragonsFuryIgnite
+        )
+    }
+}
+
 #[derive(
     Copy, Clone, Deserialize, Serialize, IntoPrimitive, TryFromPrimitive, PartialEq, Debug,
 )]
@@ -0,0 +1,268 @@
+use crate::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tf_demo_parser::demo::{
+    data::DemoTick,
+    parser::gamestateanalyser::{Class, Team},
+};
+
+/// How a map's overview image lines up with the world, as in the game's
+/// `resource/overviews/<map>.txt` used by HUD minimaps.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MapOverview {
+    pub pos_x: f32, // world x of the image's left edge
+    pub pos_y: f32, // world y of the image's top edge
+    pub scale: f32, // world units per pixel
+    #[serde(default)]
+    pub rotation: f32, // degrees clockwise about the centre of the image
+    #[serde(default = "default_overview_size")]
+    pub size: f32, // width and height of the image in pixels
+}
+
+fn default_overview_size() -> f32 {
+    1024.0
+}
+
+impl MapOverview {
+    /// Pixel coordinates of a world position on the overview image.
+    pub fn project(&self, pos: &Vec3) -> [f32; 2] {
+        let x = (pos.x - self.pos_x) / self.scale;
+        let y = (self.pos_y - pos.y) / self.scale;
+        if self.rotation == 0.0 {
+            return [x, y];
+        }
+
+        let centre = self.size / 2.0;
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (dx, dy) = (x - centre, y - centre);
+        [centre + dx * cos - dy * sin, centre + dx * sin + dy * cos]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EngagementKind {
+    Kill,
+    Damage,
+}
+
+/// Where both players were when one hurt or killed the other.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Engagement {
+    pub tick: DemoTick,
+    pub kind: EngagementKind,
+    pub weapon: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub damage: Option<u32>,
+
+    pub attacker: String, // steamid
+    pub attacker_class: Class,
+    pub attacker_team: Team,
+    pub attacker_origin: Vec3,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attacker_region: Option<String>,
+
+    pub victim: String, // steamid
+    pub victim_class: Class,
+    pub victim_team: Team,
+    pub victim_origin: Vec3,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub victim_region: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HeatPoint {
+    pub tick: DemoTick,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub px: Option<f32>, // on the map overview, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub py: Option<f32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Heatmap {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kills: Vec<HeatPoint>, // where the killer stood
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deaths: Vec<HeatPoint>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub damage_dealt: Vec<HeatPoint>, // where the attacker stood
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub damage_taken: Vec<HeatPoint>,
+}
+
+/// Point sets for drawing kill and damage heatmaps, grouped a few different ways.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Heatmaps {
+    pub map: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overview: Option<MapOverview>,
+    pub kills: Vec<Engagement>,
+
+    // Class and Team aren't Ord, so they're keyed by name to keep the output stable.
+    pub players: BTreeMap<String, Heatmap>, // steamid -> heatmap
+    pub classes: BTreeMap<String, Heatmap>,
+    pub teams: BTreeMap<String, Heatmap>,
+}
+
+impl Heatmaps {
+    pub fn new(map: String, overview: Option<MapOverview>, engagements: Vec<Engagement>) -> Self {
+        let mut heatmaps = Self {
+            map,
+            overview,
+            ..Default::default()
+        };
+
+        for e in engagements {
+            let attacker = heatmaps.point(e.tick, &e.attacker_origin);
+            let victim = heatmaps.point(e.tick, &e.victim_origin);
+
+            type Points = fn(&mut Heatmap) -> &mut Vec<HeatPoint>;
+            let (attacker_points, victim_points): (Points, Points) = match e.kind {
+                EngagementKind::Kill => (|h| &mut h.kills, |h| &mut h.deaths),
+                EngagementKind::Damage => (|h| &mut h.damage_dealt, |h| &mut h.damage_taken),
+            };
+            for h in [
+                heatmaps.players.entry(e.attacker.clone()).or_default(),
+                heatmaps
+                    .classes
+                    .entry(e.attacker_class.to_string())
+                    .or_default(),
+                heatmaps
+                    .teams
+                    .entry(team_name(e.attacker_team))
+                    .or_default(),
+            ] {
+                attacker_points(h).push(attacker.clone());
+            }
+            for h in [
+                heatmaps.players.entry(e.victim.clone()).or_default(),
+                heatmaps
+                    .classes
+                    .entry(e.victim_class.to_string())
+                    .or_default(),
+                heatmaps.teams.entry(team_name(e.victim_team)).or_default(),
+            ] {
+                victim_points(h).push(victim.clone());
+            }
+
+            if e.kind == EngagementKind::Kill {
+                heatmaps.kills.push(e);
+            }
+        }
+
+        heatmaps
+    }
+
+    fn point(&self, tick: DemoTick, pos: &Vec3) -> HeatPoint {
+        let pixel = self.overview.as_ref().map(|o| o.project(pos));
+        HeatPoint {
+            tick,
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            px: pixel.map(|p| p[0]),
+            py: pixel.map(|p| p[1]),
+        }
+    }
+}
+
+// The same lowercase name Team serializes as.
+fn team_name(team: Team) -> String {
+    team.to_string().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overview(rotation: f32) -> MapOverview {
+        MapOverview {
+            pos_x: -1000.0,
+            pos_y: 1000.0,
+            scale: 2.0,
+            rotation,
+            size: 1000.0,
+        }
+    }
+
+    fn assert_near(a: [f32; 2], b: [f32; 2]) {
+        assert!(
+            (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn projects_onto_overview() {
+        let o = overview(0.0);
+        assert_near(o.project(&Vec3::new(-1000.0, 1000.0, 0.0)), [0.0, 0.0]);
+        assert_near(o.project(&Vec3::new(0.0, 0.0, 500.0)), [500.0, 500.0]);
+        assert_near(
+            o.project(&Vec3::new(1000.0, -1000.0, 0.0)),
+            [1000.0, 1000.0],
+        );
+    }
+
+    #[test]
+    fn projects_rotated_about_centre() {
+        let o = overview(90.0);
+        // The top left corner turns clockwise to the top right.
+        assert_near(o.project(&Vec3::new(-1000.0, 1000.0, 0.0)), [1000.0, 0.0]);
+        assert_near(o.project(&Vec3::new(0.0, 0.0, 0.0)), [500.0, 500.0]);
+    }
+
+    #[test]
+    fn groups_engagements_by_name() {
+        let engagement =
+            |kind, attacker_class, attacker_team, victim_class, victim_team| Engagement {
+                tick: DemoTick::from(1),
+                kind,
+                weapon: "shotgun".into(),
+                damage: None,
+                attacker: "a".into(),
+                attacker_class,
+                attacker_team,
+                attacker_origin: Vec3::new(0.0, 0.0, 0.0),
+                attacker_region: None,
+                victim: "b".into(),
+                victim_class,
+                victim_team,
+                victim_origin: Vec3::new(10.0, 0.0, 0.0),
+                victim_region: None,
+            };
+        let heatmaps = Heatmaps::new(
+            "cp_test".into(),
+            None,
+            vec![
+                engagement(
+                    EngagementKind::Kill,
+                    Class::Soldier,
+                    Team::Red,
+                    Class::Scout,
+                    Team::Blue,
+                ),
+                engagement(
+                    EngagementKind::Damage,
+                    Class::Scout,
+                    Team::Blue,
+                    Class::Medic,
+                    Team::Red,
+                ),
+            ],
+        );
+
+        assert_eq!(heatmaps.kills.len(), 1);
+        assert_eq!(
+            heatmaps.classes.keys().collect::<Vec<_>>(),
+            ["medic", "scout", "soldier"]
+        );
+        assert_eq!(heatmaps.teams.keys().collect::<Vec<_>>(), ["blue", "red"]);
+        assert_eq!(heatmaps.teams["red"].kills.len(), 1);
+        assert_eq!(heatmaps.teams["red"].damage_taken.len(), 1);
+        assert_eq!(heatmaps.classes["scout"].deaths.len(), 1);
+        assert_eq!(heatmaps.classes["scout"].damage_dealt.len(), 1);
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tf_demo_parser::demo::data::DemoTick;
+
+// Only the most recent few interactions matter for crediting a death.
+const HISTORY_LEN: usize = 8;
+
+/// How an enemy last affected a player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InteractionKind {
+    Damage,
+    Knockback, // blast damage or a loose cannon shot
+    Airblast,
+}
+
+#[derive(Clone, Debug)]
+pub struct Interaction {
+    pub tick: DemoTick,
+    pub attacker: String, // steamid
+    pub weapon: &'static str,
+    pub kind: InteractionKind,
+}
+
+/// Recent damage and pushes a player received from enemies, newest last.
+#[derive(Clone, Debug, Default)]
+pub struct InteractionHistory(VecDeque<Interaction>);
+
+impl InteractionHistory {
+    pub fn record(
+        &mut self,
+        tick: DemoTick,
+        attacker: String,
+        weapon: &'static str,
+        kind: InteractionKind,
+    ) {
+        if self.0.len() == HISTORY_LEN {
+            self.0.pop_front();
+        }
+        self.0.push_back(Interaction {
+            tick,
+            attacker,
+            weapon,
+            kind,
+        });
+    }
+
+    // The most recent interaction no more than `window` ticks before `tick`.
+    pub fn last_within(&self, tick: DemoTick, window: u32) -> Option<&Interaction> {
+        self.0
+            .back()
+            .filter(|i| u32::from(tick).saturating_sub(u32::from(i.tick)) <= window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_latest_interaction_in_the_window() {
+        let mut history = InteractionHistory::default();
+        assert!(history.last_within(DemoTick::from(100), 50).is_none());
+
+        history.record(
+            DemoTick::from(10),
+            "a".into(),
+            "rocketlauncher",
+            InteractionKind::Knockback,
+        );
+        history.record(
+            DemoTick::from(40),
+            "b".into(),
+            "flamethrower",
+            InteractionKind::Airblast,
+        );
+
+        let last = history.last_within(DemoTick::from(90), 50).unwrap();
+        assert_eq!(
+            (last.attacker.as_str(), last.kind),
+            ("b", InteractionKind::Airblast)
+        );
+        // Older interactions don't count once the latest is out of the window.
+        assert!(history.last_within(DemoTick::from(91), 50).is_none());
+    }
+
+    #[test]
+    fn keeps_only_recent_interactions() {
+        let mut history = InteractionHistory::default();
+        for t in 0..HISTORY_LEN as u32 + 3 {
+            history.record(
+                DemoTick::from(t),
+                t.to_string(),
+                "shotgun",
+                InteractionKind::Damage,
+            );
+        }
+        assert_eq!(history.0.len(), HISTORY_LEN);
+        assert_eq!(history.0.front().unwrap().attacker, "3");
+        assert_eq!(
+            history.last_within(DemoTick::from(10), 0).unwrap().attacker,
+            "10"
+        );
+    }
+}
@@ -1,15 +1,31 @@
+pub mod bsp;
+mod building;
 mod entity;
+mod fight;
 mod game;
+pub mod heatmap;
+mod history;
+pub mod options;
 mod player;
+pub mod positions;
 mod props;
+mod recap;
+pub mod region;
+pub mod render;
 mod stats;
 pub mod summarizer;
+mod trajectory;
+mod visibility;
 mod weapon;
 
 use crate::schema::Schema;
+use options::ParseOptions;
 use serde::{Deserialize, Serialize};
 use summarizer::DemoSummary;
-use tf_demo_parser::{demo::header::Header, Demo, DemoParser};
+use tf_demo_parser::{
+    demo::{data::DemoTick, header::Header},
+    Demo, DemoParser,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DemoOutput {
@@ -23,8 +39,16 @@ pub struct DemoOutput {
 }
 
 pub fn parse(buffer: &[u8], schema: &Schema) -> tf_demo_parser::Result<DemoOutput> {
+    parse_with_options(buffer, schema, &ParseOptions::default())
+}
+
+pub fn parse_with_options(
+    buffer: &[u8],
+    schema: &Schema,
+    options: &ParseOptions,
+) -> tf_demo_parser::Result<DemoOutput> {
     let demo = Demo::new(buffer);
-    let handler = summarizer::MatchAnalyzer::new(schema);
+    let handler = summarizer::MatchAnalyzer::with_options(schema, options.clone());
     let stream = demo.get_stream();
     let parser = DemoParser::new_with_analyser(stream, handler);
 
@@ -41,6 +65,55 @@ pub fn is_zero(num: &u32) -> bool {
     *num == 0
 }
 
+pub fn is_zero_f32(num: &f32) -> bool {
+    *num == 0.0
+}
+
 pub fn is_false(b: &bool) -> bool {
     !(*b)
 }
+
+/// For use with serde's [serialize_with] attribute
+pub fn ordered_map<S, K: Ord + Serialize, V: Serialize>(
+    value: &std::collections::HashMap<K, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let ordered: std::collections::BTreeMap<_, _> = value.iter().collect();
+    ordered.serialize(serializer)
+}
+
+// Demos don't record every tick, so a sample is due once `interval` ticks have passed since the
+// last one rather than on exact multiples. Gives the ticks actually elapsed, or 0 for the first.
+pub fn sample_due(last: &mut Option<DemoTick>, tick: DemoTick, interval: u32) -> Option<u32> {
+    if interval == 0 {
+        return None;
+    }
+    let elapsed = match *last {
+        Some(last) => u32::from(tick).saturating_sub(u32::from(last)),
+        None => 0,
+    };
+    if last.is_some() && elapsed < interval {
+        return None;
+    }
+    *last = Some(tick);
+    Some(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_use_elapsed_ticks() {
+        let mut last = None;
+        let due: Vec<_> = [5, 7, 12, 13, 20]
+            .into_iter()
+            .map(|t| sample_due(&mut last, DemoTick::from(t), 6))
+            .collect();
+        assert_eq!(due, [Some(0), None, Some(7), None, Some(8)]);
+        assert_eq!(sample_due(&mut last, DemoTick::from(100), 0), None);
+    }
+}
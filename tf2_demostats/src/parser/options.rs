@@ -0,0 +1,258 @@
+use crate::parser::{heatmap::MapOverview, region::Region};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+/// Optional outputs and tunables for a parse. The defaults produce the standard summary.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// Include every projectile's trajectory and outcome in each round.
+    pub projectiles: bool,
+
+    /// Include where kills, deaths, uber pops and building placements happened in each round.
+    pub map_events: bool,
+
+    pub airshots: AirshotOptions,
+
+    pub environment: EnvironmentOptions,
+
+    pub death_recaps: DeathRecapOptions,
+
+    pub distance: DistanceOptions,
+
+    pub fights: FightOptions,
+
+    pub positions: PositionOptions,
+
+    pub heatmaps: HeatmapOptions,
+
+    pub regions: RegionOptions,
+
+    pub geometry: GeometryOptions,
+
+    pub visibility: VisibilityOptions,
+
+    pub dispensers: DispenserOptions,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AirshotOptions {
+    /// Minimum height in units of the victim above where they last stood.
+    pub min_height: f32,
+    /// Minimum number of ticks the victim must have been airborne.
+    pub min_airtime: u32,
+}
+
+impl Default for AirshotOptions {
+    fn default() -> Self {
+        Self {
+            min_height: 170.0,
+            min_airtime: 16,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentOptions {
+    /// Seconds after an enemy last hurt or pushed a player that an environmental death is still
+    /// credited to them.
+    pub kill_window: f32,
+}
+
+impl Default for EnvironmentOptions {
+    fn default() -> Self {
+        Self { kill_window: 10.0 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeathRecapOptions {
+    /// Seconds of damage and health history to include before each death.
+    pub window: f32,
+}
+
+impl Default for DeathRecapOptions {
+    fn default() -> Self {
+        Self { window: 10.0 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HeatmapOptions {
+    /// Include kill and damage positions for drawing heatmaps.
+    pub enabled: bool,
+    /// Overview image placement by map name, to also give positions in pixels.
+    pub overviews: HashMap<String, MapOverview>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct GeometryOptions {
+    /// Directory of `<map>.bsp` files to load level geometry from. Without one, only players,
+    /// buildings and projectiles are solid.
+    pub maps_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VisibilityOptions {
+    /// Ticks between line of sight checks, which only happen when level geometry is loaded.
+    /// 0 turns them off.
+    pub interval: u32,
+    /// Field of view in degrees, as a cone around where a player is looking.
+    pub fov: f32,
+    /// Seconds a victim must have been in the killer's view for the kill not to count as being
+    /// on an unseen target.
+    pub unseen_window: f32,
+}
+
+impl Default for VisibilityOptions {
+    fn default() -> Self {
+        Self {
+            interval: 4,
+            fov: 90.0,
+            unseen_window: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DispenserOptions {
+    /// Ticks between checks for who is standing at a dispenser. 0 turns them off.
+    pub interval: u32,
+}
+
+impl Default for DispenserOptions {
+    fn default() -> Self {
+        Self { interval: 8 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RegionOptions {
+    /// Named callouts by map name, used to tag events and count time and kills per region.
+    pub maps: HashMap<String, Vec<Region>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PositionOptions {
+    /// Include sampled positions of every player, building and projectile.
+    pub enabled: bool,
+    /// Ticks between samples.
+    pub interval: u32,
+}
+
+impl Default for PositionOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FightOptions {
+    /// Seconds without any damage or kills that end a fight.
+    pub lull: f32,
+    /// Seconds after a kill that killing the killer still counts as a trade.
+    pub trade_window: f32,
+    /// Damage a fight without any kills needs before it is reported.
+    pub min_damage: u32,
+}
+
+impl Default for FightOptions {
+    fn default() -> Self {
+        Self {
+            lull: 10.0,
+            trade_window: 5.0,
+            min_damage: 200,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DistanceOptions {
+    /// Ranges damage and kills are bucketed into, nearest first.
+    pub ranges: Vec<DistanceRange>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DistanceRange {
+    pub name: String,
+    /// Furthest distance in units that still falls in this range, or none for no limit.
+    pub max: Option<f32>,
+}
+
+impl DistanceOptions {
+    /// Name of the first range `distance` falls in.
+    pub fn range(&self, distance: f32) -> Option<&str> {
+        self.ranges
+            .iter()
+            .find(|r| r.max.is_none_or(|max| distance <= max))
+            .map(|r| r.name.as_str())
+    }
+}
+
+impl Default for DistanceOptions {
+    // Hitscan damage starts falling off at 512 units and bottoms out at 1024.
+    fn default() -> Self {
+        Self {
+            ranges: vec![
+                DistanceRange {
+                    name: "close".into(),
+                    max: Some(512.0),
+                },
+                DistanceRange {
+                    name: "mid".into(),
+                    max: Some(1024.0),
+                },
+                DistanceRange {
+                    name: "long".into(),
+                    max: None,
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_distances_by_default() {
+        let options = DistanceOptions::default();
+        assert_eq!(options.range(0.0), Some("close"));
+        assert_eq!(options.range(512.0), Some("close"));
+        assert_eq!(options.range(512.5), Some("mid"));
+        assert_eq!(options.range(1024.0), Some("mid"));
+        assert_eq!(options.range(5000.0), Some("long"));
+    }
+
+    #[test]
+    fn uses_the_first_range_that_fits() {
+        let options: DistanceOptions = serde_json::from_str(
+            r#"{"ranges": [
+                {"name": "melee", "max": 100},
+                {"name": "near", "max": 50},
+                {"name": "far", "max": 1000}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(options.range(50.0), Some("melee"));
+        assert_eq!(options.range(500.0), Some("far"));
+        // Past the last limit with no open ended range to fall into.
+        assert_eq!(options.range(1500.0), None);
+        assert_eq!(DistanceOptions { ranges: vec![] }.range(0.0), None);
+    }
+}
@@ -1,9 +1,16 @@
 use crate::{
     Vec3,
     parser::{
+        entity::ProjectileType,
         game::{DamageType, Death, RoundState},
+        history::InteractionHistory,
         is_false, is_zero,
-        stats::Stats,
+        options::AirshotOptions,
+        ordered_map,
+        recap::DamageLog,
+        stats::{DamageKind, Stats},
+        trajectory::ProjectileTrack,
+        weapon::SentryFire,
     },
     schema::{Attribute, Item},
 };
@@ -11,23 +18,13 @@ use enumset::EnumSet;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tf_demo_parser::demo::{
-    data::DemoTick, gameevent_gen::PlayerHurtEvent, message::packetentities::EntityId,
-    parser::gamestateanalyser::Class,
+    data::DemoTick,
+    gameevent_gen::PlayerHurtEvent,
+    message::packetentities::EntityId,
+    parser::gamestateanalyser::{Class, UserId},
 };
 use tracing::error;
 
-/// For use with serde's [serialize_with] attribute
-fn ordered_map<S, K: Ord + Serialize, V: Serialize>(
-    value: &HashMap<K, V>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    let ordered: std::collections::BTreeMap<_, _> = value.iter().collect();
-    ordered.serialize(serializer)
-}
-
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct PlayerSummary {
     pub name: String,
@@ -48,6 +45,13 @@ pub struct PlayerSummary {
     #[serde(serialize_with = "ordered_map")]
     pub weapons: HashMap<String, Stats>,
 
+    // Projectiles this player reflected, by the type of projectile
+    #[serde(
+        serialize_with = "ordered_map",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub reflected_projectiles: HashMap<ProjectileType, u32>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scoreboard_kills: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -73,10 +77,8 @@ pub struct PlayerSummary {
     //pub healing_taken: u32,
     //pub health_packs: u32,
     //pub healing_packs: u32, // total healing from packs
-    //pub extinguishes: u32,
     //pub building_built: u32,
     //pub buildings_destroyed: u32,
-    //pub teleports: u32,
     //pub support: u32,
     //pub killstreaks: Vec<Killstreak>,
     #[serde(skip_serializing_if = "is_false")]
@@ -96,8 +98,33 @@ pub struct PlayerSummary {
     #[serde(skip)]
     pub in_water: bool,
     #[serde(skip)]
+    pub movement_tick: DemoTick, // last time movement was accounted for
+    #[serde(skip)]
     pub started_flying: DemoTick,
     #[serde(skip)]
+    pub grounded_z: f32, // height of the ground the player last stood on
+    #[serde(skip)]
+    pub blast_jump_start: Option<(DemoTick, Vec3)>,
+    #[serde(skip)]
+    pub recent_attackers: InteractionHistory,
+    #[serde(skip)]
+    pub damage_log: DamageLog,
+    #[serde(skip)]
+    pub in_view_since: HashMap<String, DemoTick>, // enemy steamid -> when they came into view
+    #[serde(skip)]
+    pub ignited: DemoTick,
+    #[serde(skip)]
+    pub afterburn_tick: DemoTick, // last hurt counted as afterburn
+    #[serde(skip)]
+    pub started_bleeding: DemoTick,
+
+    // Weapon that last set this player on fire or made them bleed, keyed by the attacker, so
+    // damage over time can be credited to it.
+    #[serde(skip)]
+    pub afterburn_sources: HashMap<UserId, &'static str>,
+    #[serde(skip)]
+    pub bleed_sources: HashMap<UserId, &'static str>,
+    #[serde(skip)]
     pub class: Class,
     #[serde(skip)]
     pub health: u32,
@@ -122,6 +149,27 @@ impl PlayerSummary {
         !self.on_ground && !self.in_water
     }
 
+    // Height above where the player last stood, if they've been in the air high and long enough
+    // by `tick` to be airshot.
+    pub fn airshot_height(&self, tick: DemoTick, options: &AirshotOptions) -> Option<f32> {
+        let height = self.origin.z - self.grounded_z;
+        let airtime = u32::from(tick).saturating_sub(u32::from(self.started_flying));
+
+        (self.in_air() && airtime > options.min_airtime && height >= options.min_height)
+            .then_some(height)
+    }
+
+    // Afterburn hits every `period` ticks from when the player caught fire. Whether a burn at
+    // `tick` lands on one of those hits, within `slack` ticks, that hasn't been counted yet.
+    pub fn afterburn_due(&self, tick: DemoTick, period: u32, slack: u32) -> bool {
+        let period = period.max(1);
+        let slot = |tick: DemoTick| {
+            let elapsed = u32::from(tick).checked_sub(u32::from(self.ignited))? + slack;
+            (elapsed >= period && elapsed % period <= 2 * slack).then_some(elapsed / period)
+        };
+        slot(tick).is_some_and(|due| slot(self.afterburn_tick) != Some(due))
+    }
+
     pub fn class_stats(&mut self) -> &mut Stats {
         self.classes.entry(self.class).or_default()
     }
@@ -142,6 +190,189 @@ impl PlayerSummary {
         self.weapon_stats(weapon).handle_shot_hit();
     }
 
+    pub fn handle_projectile_hit(&mut self, track: &ProjectileTrack) {
+        self.stats.handle_projectile_hit(track);
+        self.class_stats().handle_projectile_hit(track);
+        self.weapon_stats(&track.weapon)
+            .handle_projectile_hit(track);
+    }
+
+    pub fn handle_ranged_damage(&mut self, weapon: &str, range: &str, hurt: &PlayerHurtEvent) {
+        self.stats.handle_ranged_damage(range, hurt);
+        self.class_stats().handle_ranged_damage(range, hurt);
+        self.weapon_stats(weapon).handle_ranged_damage(range, hurt);
+    }
+
+    pub fn handle_ranged_hit(&mut self, weapon: &str, range: &str) {
+        self.stats.handle_ranged_hit(range);
+        self.class_stats().handle_ranged_hit(range);
+        self.weapon_stats(weapon).handle_ranged_hit(range);
+    }
+
+    pub fn handle_ranged_kill(&mut self, weapon: &str, range: &str, distance: f32) {
+        self.stats.handle_ranged_kill(range, distance);
+        self.class_stats().handle_ranged_kill(range, distance);
+        self.weapon_stats(weapon)
+            .handle_ranged_kill(range, distance);
+    }
+
+    pub fn handle_region_time(&mut self, region: &str, seconds: f32) {
+        self.stats.handle_region_time(region, seconds);
+        self.class_stats().handle_region_time(region, seconds);
+    }
+
+    pub fn handle_region_kill(&mut self, weapon: &str, region: &str) {
+        self.stats.handle_region_kill(region);
+        self.class_stats().handle_region_kill(region);
+        self.weapon_stats(weapon).handle_region_kill(region);
+    }
+
+    pub fn handle_region_death(&mut self, region: &str) {
+        self.stats.handle_region_death(region);
+        self.class_stats().handle_region_death(region);
+    }
+
+    pub fn handle_airshot_hit(&mut self, weapon: &str) {
+        self.stats.handle_airshot_hit();
+        self.class_stats().handle_airshot_hit();
+        self.weapon_stats(weapon).handle_airshot_hit();
+    }
+
+    pub fn handle_self_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.stats.handle_self_damage(hurt);
+        self.class_stats().handle_self_damage(hurt);
+    }
+
+    pub fn handle_fall_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.stats.handle_fall_damage(hurt);
+        self.class_stats().handle_fall_damage(hurt);
+    }
+
+    pub fn handle_environment_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.stats.handle_environment_damage(hurt);
+        self.class_stats().handle_environment_damage(hurt);
+    }
+
+    pub fn handle_environment_death(&mut self) {
+        self.stats.handle_environment_death();
+        self.class_stats().handle_environment_death();
+    }
+
+    pub fn handle_environmental_kill(&mut self, weapon: Option<&str>) {
+        self.stats.handle_environmental_kill();
+        self.class_stats().handle_environmental_kill();
+        if let Some(weapon) = weapon {
+            self.weapon_stats(weapon).handle_environmental_kill();
+        }
+    }
+
+    pub fn handle_trade(&mut self, weapon: &str) {
+        self.stats.handle_trade();
+        self.class_stats().handle_trade();
+        self.weapon_stats(weapon).handle_trade();
+    }
+
+    pub fn handle_traded_death(&mut self) {
+        self.stats.handle_traded_death();
+        self.class_stats().handle_traded_death();
+    }
+
+    pub fn handle_first_pick(&mut self, weapon: &str) {
+        self.stats.handle_first_pick();
+        self.class_stats().handle_first_pick();
+        self.weapon_stats(weapon).handle_first_pick();
+    }
+
+    pub fn handle_first_death(&mut self) {
+        self.stats.handle_first_death();
+        self.class_stats().handle_first_death();
+    }
+
+    pub fn handle_blast_jump(&mut self, airtime: f32, distance: f32) {
+        self.stats.handle_blast_jump(airtime, distance);
+        self.class_stats().handle_blast_jump(airtime, distance);
+    }
+
+    pub fn handle_movement(&mut self, distance: f32, seconds: f32) {
+        self.stats.handle_movement(distance, seconds);
+        self.class_stats().handle_movement(distance, seconds);
+    }
+
+    pub fn handle_visibility_sample(
+        &mut self,
+        tick: DemoTick,
+        seconds: f32,
+        visible: bool,
+        enemies_in_view: &[&str],
+    ) {
+        self.in_view_since
+            .retain(|enemy, _| enemies_in_view.contains(&enemy.as_str()));
+        for enemy in enemies_in_view {
+            self.in_view_since.entry(enemy.to_string()).or_insert(tick);
+        }
+
+        let count = enemies_in_view.len() as u32;
+        self.stats.handle_visibility_sample(seconds, visible, count);
+        self.class_stats()
+            .handle_visibility_sample(seconds, visible, count);
+    }
+
+    // How long an enemy has been continuously in view, as of the last sample.
+    pub fn time_in_view(&self, enemy: &str, tick: DemoTick) -> u32 {
+        self.in_view_since
+            .get(enemy)
+            .map_or(0, |since| u32::from(tick).saturating_sub(u32::from(*since)))
+    }
+
+    // Whether an enemy killed at `tick` hadn't been in view for at least `window` ticks.
+    pub fn unseen(&self, enemy: &str, tick: DemoTick, window: u32) -> bool {
+        self.time_in_view(enemy, tick) < window
+    }
+
+    pub fn handle_unseen_kill(&mut self, weapon: &str) {
+        self.stats.handle_unseen_kill();
+        self.class_stats().handle_unseen_kill();
+        self.weapon_stats(weapon).handle_unseen_kill();
+    }
+
+    // Credits time spent in whatever state the player was in before this update.
+    pub fn handle_movement_time(&mut self, seconds: f32, crouched: bool) {
+        let (airborne, in_water) = (self.in_air(), self.in_water);
+        self.stats
+            .handle_movement_time(seconds, airborne, in_water, crouched);
+        self.class_stats()
+            .handle_movement_time(seconds, airborne, in_water, crouched);
+    }
+
+    // Start timing a blast jump when the condition is applied, and credit it once it clears.
+    pub fn handle_blast_jump_condition(
+        &mut self,
+        tick: DemoTick,
+        was_jumping: bool,
+        jumping: bool,
+        interval: f32,
+    ) {
+        if jumping && !was_jumping {
+            self.blast_jump_start = Some((tick, self.origin));
+        } else if !jumping && let Some((start, origin)) = self.blast_jump_start.take() {
+            let airtime = (u32::from(tick) - u32::from(start)) as f32 * interval;
+            let distance = (self.origin - origin).norm();
+            self.handle_blast_jump(airtime, distance);
+        }
+    }
+
+    pub fn handle_blast_jump_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.stats.handle_blast_jump_damage(hurt);
+        self.class_stats().handle_blast_jump_damage(hurt);
+    }
+
+    pub fn handle_blast_jump_kill(&mut self, weapon: &str, market_garden: bool) {
+        self.stats.handle_blast_jump_kill(market_garden);
+        self.class_stats().handle_blast_jump_kill(market_garden);
+        self.weapon_stats(weapon)
+            .handle_blast_jump_kill(market_garden);
+    }
+
     pub fn handle_object_built(&mut self, weapon: &str) {
         self.stats.handle_object_built();
         // This can only happen as engi, so no class_stats() update
@@ -154,16 +385,106 @@ impl PlayerSummary {
         self.weapon_stats(weapon).handle_object_destroyed();
     }
 
+    pub fn handle_teleport_provided(&mut self) {
+        self.stats.handle_teleport_provided();
+        self.class_stats().handle_teleport_provided();
+    }
+
+    pub fn handle_teleport_taken(&mut self) {
+        self.stats.handle_teleport_taken();
+        self.class_stats().handle_teleport_taken();
+    }
+
+    pub fn handle_dispenser_supply(&mut self, healing: u32, seconds: f32) {
+        self.stats.handle_dispenser_supply(healing, seconds);
+        self.class_stats().handle_dispenser_supply(healing, seconds);
+    }
+
+    pub fn handle_dispenser_visit(&mut self, healing: u32, seconds: f32) {
+        self.stats.handle_dispenser_visit(healing, seconds);
+        self.class_stats().handle_dispenser_visit(healing, seconds);
+    }
+
+    pub fn handle_sentry_damage(&mut self, damage: u32, fire: SentryFire) {
+        self.stats.handle_sentry_damage(damage, fire);
+        self.class_stats().handle_sentry_damage(damage, fire);
+    }
+
+    pub fn handle_sentry_kill(&mut self, fire: SentryFire) {
+        self.stats.handle_sentry_kill(fire);
+        self.class_stats().handle_sentry_kill(fire);
+    }
+
+    pub fn handle_wrangler_shield(&mut self, seconds: f32) {
+        self.stats.handle_wrangler_shield(seconds);
+        self.class_stats().handle_wrangler_shield(seconds);
+    }
+
+    pub fn handle_sapper_placed(&mut self) {
+        self.stats.handle_sapper_placed();
+        self.class_stats().handle_sapper_placed();
+    }
+
+    pub fn handle_sapped_building_destroyed(&mut self) {
+        self.stats.handle_sapped_building_destroyed();
+        self.class_stats().handle_sapped_building_destroyed();
+    }
+
+    pub fn handle_sapper_removed(&mut self, weapon: &str) {
+        self.stats.handle_sapper_removed();
+        self.class_stats().handle_sapper_removed();
+        self.weapon_stats(weapon).handle_sapper_removed();
+    }
+
+    pub fn handle_airblast(&mut self, weapon: &str) {
+        self.stats.handle_airblast();
+        self.class_stats().handle_airblast();
+        self.weapon_stats(weapon).handle_airblast();
+    }
+
+    pub fn handle_reflect(&mut self, weapon: &str, kind: ProjectileType) {
+        self.stats.handle_reflect();
+        self.class_stats().handle_reflect();
+        self.weapon_stats(weapon).handle_reflect();
+        *self.reflected_projectiles.entry(kind).or_default() += 1;
+    }
+
+    pub fn handle_reflect_damage(&mut self, weapon: &str, hurt: &PlayerHurtEvent) {
+        self.stats.handle_reflect_damage(hurt);
+        self.class_stats().handle_reflect_damage(hurt);
+        self.weapon_stats(weapon).handle_reflect_damage(hurt);
+    }
+
+    pub fn handle_reflect_kill(&mut self, weapon: &str) {
+        self.stats.handle_reflect_kill();
+        self.class_stats().handle_reflect_kill();
+        self.weapon_stats(weapon).handle_reflect_kill();
+    }
+
+    pub fn handle_push(&mut self, weapon: &str) {
+        self.stats.handle_push();
+        self.class_stats().handle_push();
+        self.weapon_stats(weapon).handle_push();
+    }
+
+    pub fn handle_extinguish(&mut self, weapon: &str) {
+        self.stats.handle_extinguish();
+        self.class_stats().handle_extinguish();
+        self.weapon_stats(weapon).handle_extinguish();
+    }
+
     pub fn handle_damage_dealt(
         &mut self,
         weapon: &str,
         hurt: &PlayerHurtEvent,
         damage_type: DamageType,
+        kind: DamageKind,
     ) {
-        self.stats.handle_damage_dealt(hurt, damage_type);
-        self.class_stats().handle_damage_dealt(hurt, damage_type);
+        self.stats.handle_damage_dealt(hurt, damage_type, kind);
+        self.class_stats()
+            .handle_damage_dealt(hurt, damage_type, kind);
         self.weapon_stats(weapon)
-            .handle_damage_dealt(hurt, damage_type);
+            .handle_damage_dealt(hurt, damage_type, kind);
     }
 
     pub fn handle_damage_taken(
@@ -272,6 +593,7 @@ impl PlayerSummary {
         self.stats = Stats::default();
         self.classes.clear();
         self.weapons.clear();
+        self.reflected_projectiles.clear();
         // scoreboard_healing is temporary and reset elsewhere.
         // postround_kills, assists, deaths are reset by virtue of Stats::default()
         self.suicides = 0; // Reset suicides per round
@@ -283,3 +605,163 @@ impl PlayerSummary {
         // to be per-round from source.
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pyro() -> PlayerSummary {
+        PlayerSummary {
+            class: Class::Pyro,
+            ..Default::default()
+        }
+    }
+
+    fn flying(z: f32, since: u32) -> PlayerSummary {
+        PlayerSummary {
+            origin: Vec3::new(0.0, 0.0, z),
+            grounded_z: 100.0,
+            started_flying: DemoTick::from(since),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn airshots_need_height_over_the_ground_and_airtime() {
+        let options = AirshotOptions::default();
+        let tick = DemoTick::from(100);
+        let high = 100.0 + options.min_height;
+        let long = 100 - options.min_airtime - 1;
+
+        assert_eq!(
+            flying(high, long).airshot_height(tick, &options),
+            Some(options.min_height)
+        );
+        assert_eq!(
+            flying(high - 1.0, long).airshot_height(tick, &options),
+            None
+        );
+        assert_eq!(flying(high, long + 1).airshot_height(tick, &options), None);
+
+        // Standing on something high up, or swimming, isn't being in the air.
+        let mut grounded = flying(high, long);
+        grounded.on_ground = true;
+        assert_eq!(grounded.airshot_height(tick, &options), None);
+        let mut swimming = flying(high, long);
+        swimming.in_water = true;
+        assert_eq!(swimming.airshot_height(tick, &options), None);
+    }
+
+    #[test]
+    fn blast_jumps_are_timed_from_the_condition() {
+        let mut soldier = PlayerSummary {
+            class: Class::Soldier,
+            ..Default::default()
+        };
+        soldier.handle_blast_jump_condition(DemoTick::from(10), false, true, 0.015);
+        // Still jumping doesn't restart the clock.
+        soldier.origin = Vec3::new(300.0, 400.0, 0.0);
+        soldier.handle_blast_jump_condition(DemoTick::from(50), true, true, 0.015);
+        assert_eq!(soldier.stats.blast_jumps, 0);
+
+        soldier.handle_blast_jump_condition(DemoTick::from(110), true, false, 0.015);
+        assert_eq!(soldier.stats.blast_jumps, 1);
+        assert!((soldier.stats.blast_jump_airtime - 1.5).abs() < 1e-4);
+        assert_eq!(soldier.stats.blast_jump_distance, 500.0);
+        assert_eq!(soldier.classes[&Class::Soldier].blast_jumps, 1);
+
+        // Landing again without a new jump credits nothing.
+        soldier.handle_blast_jump_condition(DemoTick::from(120), false, false, 0.015);
+        assert_eq!(soldier.stats.blast_jumps, 1);
+    }
+
+    #[test]
+    fn market_gardens_are_blast_jump_kills() {
+        let mut soldier = PlayerSummary {
+            class: Class::Soldier,
+            ..Default::default()
+        };
+        soldier.handle_blast_jump_kill("rocketlauncher", false);
+        soldier.handle_blast_jump_kill("market_gardener", true);
+
+        assert_eq!(soldier.stats.blast_jump_kills, 2);
+        assert_eq!(soldier.stats.market_gardens, 1);
+        assert_eq!(soldier.weapons["market_gardener"].market_gardens, 1);
+        assert_eq!(soldier.weapons["rocketlauncher"].market_gardens, 0);
+    }
+
+    #[test]
+    fn movement_time_is_bucketed_by_state() {
+        let mut p = PlayerSummary {
+            class: Class::Scout,
+            on_ground: true,
+            ..Default::default()
+        };
+        p.handle_movement(300.0, 1.0);
+        p.handle_movement_time(1.0, true);
+        p.on_ground = false;
+        p.handle_movement(600.0, 1.0);
+        p.handle_movement_time(1.0, false);
+        p.in_water = true;
+        p.handle_movement_time(2.0, false);
+
+        let stats = &p.stats;
+        assert_eq!(stats.alive_time, 4.0);
+        assert_eq!(stats.airborne_time, 1.0);
+        assert_eq!(stats.water_time, 2.0);
+        assert_eq!(stats.crouched_time, 1.0);
+        assert_eq!(stats.distance_travelled, 900.0);
+        assert_eq!(stats.max_speed, 600.0);
+        assert_eq!(stats.avg_speed, 225.0);
+        assert_eq!(p.classes[&Class::Scout].alive_time, 4.0);
+    }
+
+    #[test]
+    fn kills_are_unseen_until_the_enemy_was_in_view_for_the_window() {
+        let mut p = PlayerSummary::default();
+        let at = DemoTick::from;
+        p.handle_visibility_sample(at(100), 0.06, false, &["b"]);
+        p.handle_visibility_sample(at(104), 0.06, false, &["b", "c"]);
+
+        assert!(p.unseen("b", at(132), 33));
+        assert!(!p.unseen("b", at(133), 33));
+        assert!(p.unseen("c", at(133), 33));
+        assert!(p.unseen("d", at(133), 33));
+
+        // Losing sight of them starts the clock over.
+        p.handle_visibility_sample(at(108), 0.06, false, &["c"]);
+        p.handle_visibility_sample(at(112), 0.06, false, &["b", "c"]);
+        assert!(p.unseen("b", at(140), 33));
+        assert!(!p.unseen("c", at(140), 33));
+    }
+
+    #[test]
+    fn reflects_count_overall_per_class_weapon_and_projectile() {
+        let mut p = pyro();
+        p.handle_reflect("flamethrower", ProjectileType::Rocket);
+        p.handle_reflect("flamethrower", ProjectileType::Rocket);
+        p.handle_reflect("degreaser", ProjectileType::Pipe);
+
+        assert_eq!(p.stats.reflects, 3);
+        assert_eq!(p.classes[&Class::Pyro].reflects, 3);
+        assert_eq!(p.weapons["flamethrower"].reflects, 2);
+        assert_eq!(p.weapons["degreaser"].reflects, 1);
+        assert_eq!(p.reflected_projectiles[&ProjectileType::Rocket], 2);
+        assert_eq!(p.reflected_projectiles[&ProjectileType::Pipe], 1);
+    }
+
+    #[test]
+    fn pushes_and_extinguishes_count_per_weapon() {
+        let mut p = pyro();
+        p.handle_push("flamethrower");
+        p.handle_extinguish("flamethrower");
+        p.handle_extinguish("manmelter");
+
+        assert_eq!(p.stats.players_pushed, 1);
+        assert_eq!(p.stats.extinguishes, 2);
+        assert_eq!(p.classes[&Class::Pyro].extinguishes, 2);
+        assert_eq!(p.weapons["flamethrower"].players_pushed, 1);
+        assert_eq!(p.weapons["manmelter"].extinguishes, 1);
+        assert_eq!(p.weapons["manmelter"].players_pushed, 0);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    Vec3,
+    parser::{entity::Player, sample_due},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tf_demo_parser::demo::{
+    data::DemoTick, message::packetentities::EntityId, parser::gamestateanalyser::Team,
+};
+
+// Positions are stored one column per field, with one entry per sample, to keep the output
+// small enough to ship to a browser.
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PlayerTrack {
+    pub tick: Vec<DemoTick>,
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+    pub z: Vec<f32>,
+    pub pitch: Vec<f32>,
+    pub yaw: Vec<f32>,
+    pub health: Vec<u32>,
+    pub class: Vec<u8>, // tf_demo_parser's Class ids
+    pub team: Vec<u8>,  // tf_demo_parser's Team ids
+    pub alive: Vec<bool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ObjectTrack {
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>, // steamid
+    pub team: u8,
+
+    pub tick: Vec<DemoTick>,
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+    pub z: Vec<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub level: Vec<u32>, // buildings only
+}
+
+impl ObjectTrack {
+    pub fn new(kind: String, owner: Option<String>, team: Team) -> Self {
+        Self {
+            kind,
+            owner,
+            team: team as u8,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, tick: DemoTick, origin: Vec3) {
+        self.tick.push(tick);
+        self.x.push(origin.x);
+        self.y.push(origin.y);
+        self.z.push(origin.z);
+    }
+}
+
+/// Everything on the map, sampled every `interval` ticks.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PositionTracks {
+    pub interval: u32,
+    pub players: BTreeMap<String, PlayerTrack>, // steamid -> track
+    pub buildings: Vec<ObjectTrack>,
+    pub projectiles: Vec<ObjectTrack>,
+
+    // Entity ids get reused, so only map ids to the tracks of objects that still exist.
+    #[serde(skip)]
+    live_buildings: HashMap<EntityId, usize>,
+    #[serde(skip)]
+    live_projectiles: HashMap<EntityId, usize>,
+    #[serde(skip)]
+    last_sample: Option<DemoTick>,
+}
+
+impl PositionTracks {
+    pub fn new(interval: u32) -> Self {
+        Self {
+            interval,
+            ..Default::default()
+        }
+    }
+
+    pub fn take_sample(&mut self, tick: DemoTick) -> bool {
+        sample_due(&mut self.last_sample, tick, self.interval).is_some()
+    }
+
+    pub fn record_player(&mut self, steamid: &str, tick: DemoTick, player: &Player) {
+        let track = self.players.entry(steamid.to_string()).or_default();
+        track.tick.push(tick);
+        track.x.push(player.origin.x);
+        track.y.push(player.origin.y);
+        track.z.push(player.origin.z);
+        track.pitch.push(player.eye.x);
+        track.yaw.push(player.eye.y);
+        track.health.push(player.health);
+        track.class.push(player.class as u8);
+        track.team.push(player.team as u8);
+        track.alive.push(player.health > 0);
+    }
+
+    // The track for a building, starting a new one if it wasn't seen before.
+    pub fn building(
+        &mut self,
+        id: EntityId,
+        new: impl FnOnce() -> ObjectTrack,
+    ) -> &mut ObjectTrack {
+        let index = *self.live_buildings.entry(id).or_insert_with(|| {
+            self.buildings.push(new());
+            self.buildings.len() - 1
+        });
+        &mut self.buildings[index]
+    }
+
+    pub fn projectile(
+        &mut self,
+        id: EntityId,
+        new: impl FnOnce() -> ObjectTrack,
+    ) -> &mut ObjectTrack {
+        let index = *self.live_projectiles.entry(id).or_insert_with(|| {
+            self.projectiles.push(new());
+            self.projectiles.len() - 1
+        });
+        &mut self.projectiles[index]
+    }
+
+    pub fn forget(&mut self, id: &EntityId) {
+        self.live_buildings.remove(id);
+        self.live_projectiles.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_after_interval_elapses() {
+        let mut positions = PositionTracks::new(4);
+        let sampled: Vec<u32> = [1, 2, 3, 6, 7, 9, 10, 14]
+            .into_iter()
+            .filter(|&t| positions.take_sample(DemoTick::from(t)))
+            .collect();
+        assert_eq!(sampled, [1, 6, 10, 14]);
+    }
+
+    #[test]
+    fn zero_interval_never_samples() {
+        let mut positions = PositionTracks::new(0);
+        assert!(!positions.take_sample(DemoTick::from(0)));
+        assert!(!positions.take_sample(DemoTick::from(100)));
+    }
+}
@@ -28,6 +28,17 @@ pub const _MAX_UPGRADE_LEVEL: SendPropIdentifier =
 
 pub const OBJECT_MAX_HEALTH: SendPropIdentifier =
     SendPropIdentifier::new("DT_BaseObject", "m_iMaxHealth");
+pub const OBJECT_CARRIED: SendPropIdentifier =
+    SendPropIdentifier::new("DT_BaseObject", "m_bCarried");
+// 0 for a teleporter entrance, 1 for an exit.
+pub const OBJECT_MODE: SendPropIdentifier =
+    SendPropIdentifier::new("DT_BaseObject", "m_iObjectMode");
+// Non-zero while a sentry is being wrangled.
+pub const SENTRY_SHIELD_LEVEL: SendPropIdentifier =
+    SendPropIdentifier::new("DT_ObjectSentrygun", "m_nShieldLevel");
+// What a sapper is attached to.
+pub const BUILT_ON: SendPropIdentifier =
+    SendPropIdentifier::new("DT_BaseObject", "m_hBuiltOnEntity");
 
 pub const ORIGIN_XY: SendPropIdentifier =
     SendPropIdentifier::new("DT_TFNonLocalPlayerExclusive", "m_vecOrigin");
@@ -105,6 +116,8 @@ pub const COSMETIC_7: SendPropIdentifier = SendPropIdentifier::new("_ST_m_hMyWea
 
 pub const RESET_PARITY: SendPropIdentifier =
     SendPropIdentifier::new("DT_TFWeaponBase", "m_bResetParity");
+pub const FLAMETHROWER_STATE: SendPropIdentifier =
+    SendPropIdentifier::new("DT_TFFlameThrower", "m_iWeaponState");
 
 // DT_TFBaseRocket is for most rockets, arrows, and maybe more?
 pub const ROCKET_ORIGIN: SendPropIdentifier =
@@ -0,0 +1,186 @@
+use crate::parser::{is_zero_f32, stats::DamageKind};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tf_demo_parser::demo::{data::DemoTick, gameevent_gen::PlayerHurtEvent};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CritType {
+    #[default]
+    None,
+    Mini,
+    Crit,
+}
+
+impl CritType {
+    pub fn from_hurt(hurt: &PlayerHurtEvent) -> Self {
+        if hurt.crit {
+            CritType::Crit
+        } else if hurt.mini_crit {
+            CritType::Mini
+        } else {
+            CritType::None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DamageInstance {
+    pub tick: DemoTick,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attacker: Option<String>, // steamid, none for the world
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weapon: Option<String>,
+    pub amount: u32,
+    pub kind: DamageKind,
+    pub crit: CritType,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub distance: f32,
+    pub health: u32, // left after the hit
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HealthSample {
+    pub tick: DemoTick,
+    pub health: u32,
+}
+
+/// Everything that happened to a player in the seconds before they died.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeathRecap {
+    pub tick: DemoTick,
+    pub victim: String, // steamid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub killer: Option<String>, // steamid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>, // where the victim died
+    pub damage: Vec<DamageInstance>,
+    pub health: Vec<HealthSample>,
+}
+
+/// Rolling record of the damage a player took and how their health changed.
+#[derive(Clone, Debug, Default)]
+pub struct DamageLog {
+    damage: VecDeque<DamageInstance>,
+    health: VecDeque<HealthSample>,
+}
+
+impl DamageLog {
+    pub fn record_damage(&mut self, instance: DamageInstance) {
+        self.damage.push_back(instance);
+    }
+
+    pub fn record_health(&mut self, tick: DemoTick, health: u32) {
+        if self.health.back().map(|s| s.health) != Some(health) {
+            self.health.push_back(HealthSample { tick, health });
+        }
+    }
+
+    // Forget anything more than `window` ticks before `tick`, but keep the health the player had
+    // as the window starts so the trajectory always has a starting point.
+    pub fn prune(&mut self, tick: DemoTick, window: u32) {
+        let expired = |t: DemoTick| u32::from(tick).saturating_sub(u32::from(t)) > window;
+
+        while self.damage.front().is_some_and(|d| expired(d.tick)) {
+            self.damage.pop_front();
+        }
+        while self.health.get(1).is_some_and(|s| expired(s.tick)) {
+            self.health.pop_front();
+        }
+    }
+
+    pub fn recap(
+        &mut self,
+        tick: DemoTick,
+        victim: String,
+        killer: Option<String>,
+        region: Option<String>,
+    ) -> DeathRecap {
+        DeathRecap {
+            tick,
+            victim,
+            killer,
+            region,
+            damage: self.damage.drain(..).collect(),
+            health: self.health.drain(..).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(t: u32) -> DemoTick {
+        DemoTick::from(t)
+    }
+
+    fn hit(t: u32, amount: u32, health: u32) -> DamageInstance {
+        DamageInstance {
+            tick: tick(t),
+            attacker: Some("attacker".into()),
+            weapon: Some("scattergun".into()),
+            amount,
+            kind: DamageKind::Direct,
+            crit: CritType::None,
+            distance: 100.0,
+            health,
+        }
+    }
+
+    fn ticks<T>(items: &[T], tick: fn(&T) -> DemoTick) -> Vec<u32> {
+        items.iter().map(|i| u32::from(tick(i))).collect()
+    }
+
+    #[test]
+    fn only_records_health_changes() {
+        let mut log = DamageLog::default();
+        log.record_health(tick(1), 125);
+        log.record_health(tick(2), 125);
+        log.record_health(tick(3), 80);
+        log.record_health(tick(4), 125);
+
+        let recap = log.recap(tick(5), "victim".into(), None, None);
+        let health: Vec<_> = recap.health.iter().map(|s| s.health).collect();
+        assert_eq!(health, [125, 80, 125]);
+        assert_eq!(ticks(&recap.health, |s| s.tick), [1, 3, 4]);
+    }
+
+    #[test]
+    fn prunes_to_the_window() {
+        let mut log = DamageLog::default();
+        for (t, health) in [(10, 125), (20, 100), (100, 60)] {
+            log.record_health(tick(t), health);
+        }
+        log.record_damage(hit(20, 25, 100));
+        log.record_damage(hit(100, 40, 60));
+
+        log.prune(tick(110), 30);
+        let recap = log.recap(tick(110), "victim".into(), Some("attacker".into()), None);
+        assert_eq!(ticks(&recap.damage, |d| d.tick), [100]);
+        // What they had going in to the window is kept, however long ago it changed.
+        assert_eq!(ticks(&recap.health, |s| s.tick), [20, 100]);
+        assert_eq!(recap.killer.as_deref(), Some("attacker"));
+    }
+
+    #[test]
+    fn keeps_the_only_health_sample() {
+        let mut log = DamageLog::default();
+        log.record_health(tick(10), 125);
+        log.prune(tick(1000), 30);
+        let recap = log.recap(tick(1000), "victim".into(), None, None);
+        assert_eq!(ticks(&recap.health, |s| s.tick), [10]);
+    }
+
+    #[test]
+    fn recaps_start_afresh() {
+        let mut log = DamageLog::default();
+        log.record_health(tick(10), 125);
+        log.record_damage(hit(10, 25, 100));
+        let first = log.recap(tick(10), "victim".into(), None, Some("mid".into()));
+        assert_eq!((first.damage.len(), first.health.len()), (1, 1));
+        assert_eq!(first.region.as_deref(), Some("mid"));
+
+        let second = log.recap(tick(20), "victim".into(), None, None);
+        assert!(second.damage.is_empty() && second.health.is_empty());
+    }
+}
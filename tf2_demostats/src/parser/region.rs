@@ -0,0 +1,228 @@
+use crate::Vec3;
+use rapier3d::prelude::{
+    ColliderBuilder, ColliderSet, QueryFilter, QueryPipeline, RigidBodySet, point, vector,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+// Shapes without a height limit cover the whole height of the world.
+const WORLD_EXTENT: f32 = 16384.0;
+
+// How far from a polygon's edge, in units, a point still counts as being on it.
+const EDGE_EPSILON: f32 = 0.01;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RegionShape {
+    /// Everything between two corners.
+    Box { min: [f32; 3], max: [f32; 3] },
+    /// An outline on the ground, optionally limited to a range of heights.
+    Polygon {
+        points: Vec<[f32; 2]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        z_min: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        z_max: Option<f32>,
+    },
+}
+
+impl RegionShape {
+    fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        match self {
+            RegionShape::Box { min, max } => (*min, *max),
+            RegionShape::Polygon {
+                points,
+                z_min,
+                z_max,
+            } => {
+                let mut min = [f32::MAX, f32::MAX, z_min.unwrap_or(-WORLD_EXTENT)];
+                let mut max = [f32::MIN, f32::MIN, z_max.unwrap_or(WORLD_EXTENT)];
+                for [x, y] in points {
+                    min[0] = min[0].min(*x);
+                    min[1] = min[1].min(*y);
+                    max[0] = max[0].max(*x);
+                    max[1] = max[1].max(*y);
+                }
+                (min, max)
+            }
+        }
+    }
+
+    // Only called for points already inside the bounds.
+    fn contains(&self, pos: &Vec3) -> bool {
+        let RegionShape::Polygon { points, .. } = self else {
+            return true;
+        };
+
+        // Count crossings of a ray from the point towards +x. Points on an edge are inside, as
+        // they are for boxes, whichever side of the shape the edge is on.
+        let mut inside = false;
+        for (i, [x1, y1]) in points.iter().enumerate() {
+            let [x2, y2] = points[(i + 1) % points.len()];
+            let cross = (x2 - x1) * (pos.y - y1) - (y2 - y1) * (pos.x - x1);
+            if cross.abs() <= EDGE_EPSILON * f32::hypot(x2 - x1, y2 - y1)
+                && (x1.min(x2)..=x1.max(x2)).contains(&pos.x)
+                && (y1.min(y2)..=y1.max(y2)).contains(&pos.y)
+            {
+                return true;
+            }
+            if (*y1 > pos.y) != (y2 > pos.y) && pos.x < x1 + (pos.y - y1) / (y2 - y1) * (x2 - x1) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+/// A named part of a map, such as "mid", "choke" or "last".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    #[serde(flatten)]
+    pub shape: RegionShape,
+}
+
+/// Point lookups against a map's regions. Where regions overlap, the one listed first wins, so
+/// small regions can be carved out of larger ones.
+pub struct RegionMap {
+    regions: Vec<Region>,
+    world: QueryPipeline,
+    collider_set: ColliderSet,
+    rigid_body_set: RigidBodySet, // unused, but needed for some APIs :\
+}
+
+impl RegionMap {
+    pub fn new(regions: Vec<Region>) -> Self {
+        let mut collider_set = ColliderSet::with_capacity(regions.len());
+        let mut handles = Vec::with_capacity(regions.len());
+        for (i, region) in regions.iter().enumerate() {
+            if let RegionShape::Polygon { points, .. } = &region.shape
+                && points.len() < 3
+            {
+                error!("Region {} needs at least 3 points", region.name);
+                continue;
+            }
+
+            // The colliders only narrow down candidates; polygons get an exact test later.
+            let (min, max) = region.shape.bounds();
+            let mut c = ColliderBuilder::cuboid(
+                (max[0] - min[0]) / 2.0,
+                (max[1] - min[1]) / 2.0,
+                (max[2] - min[2]) / 2.0,
+            )
+            .translation(vector![
+                (min[0] + max[0]) / 2.0,
+                (min[1] + max[1]) / 2.0,
+                (min[2] + max[2]) / 2.0
+            ])
+            .build();
+            c.user_data = i as u128;
+            handles.push(collider_set.insert(c));
+        }
+
+        let mut world = QueryPipeline::new();
+        world.update_incremental(&collider_set, &handles, &[], true);
+
+        Self {
+            regions,
+            world,
+            collider_set,
+            rigid_body_set: RigidBodySet::with_capacity(0),
+        }
+    }
+
+    pub fn region_at(&self, pos: &Vec3) -> Option<&str> {
+        let mut found: Option<usize> = None;
+        self.world.intersections_with_point(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &point![pos.x, pos.y, pos.z],
+            QueryFilter::new(),
+            |handle| {
+                // unwrap() safety: this is a lookup into the very set we are querying.
+                let i = self.collider_set.get(handle).unwrap().user_data as usize;
+                if found.is_none_or(|f| i < f) && self.regions[i].shape.contains(pos) {
+                    found = Some(i);
+                }
+                true
+            },
+        );
+        found.map(|i| self.regions[i].name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(name: &str, points: Vec<[f32; 2]>, z_min: Option<f32>) -> Region {
+        Region {
+            name: name.into(),
+            shape: RegionShape::Polygon {
+                points,
+                z_min,
+                z_max: None,
+            },
+        }
+    }
+
+    fn regions() -> RegionMap {
+        RegionMap::new(vec![
+            Region {
+                name: "point".into(),
+                shape: RegionShape::Box {
+                    min: [-10.0, -10.0, 0.0],
+                    max: [10.0, 10.0, 100.0],
+                },
+            },
+            // A triangle, so its bounding box has corners outside it.
+            polygon(
+                "mid",
+                vec![[-100.0, -100.0], [100.0, -100.0], [0.0, 100.0]],
+                None,
+            ),
+            polygon(
+                "upper",
+                vec![[200.0, 0.0], [300.0, 0.0], [300.0, 100.0]],
+                Some(50.0),
+            ),
+            polygon("broken", vec![[0.0, 0.0], [1.0, 1.0]], None),
+        ])
+    }
+
+    #[test]
+    fn finds_regions() {
+        let map = regions();
+        assert_eq!(map.region_at(&Vec3::new(0.0, -50.0, 0.0)), Some("mid"));
+        assert_eq!(map.region_at(&Vec3::new(90.0, 90.0, 0.0)), None);
+        assert_eq!(map.region_at(&Vec3::new(290.0, 10.0, 60.0)), Some("upper"));
+        assert_eq!(map.region_at(&Vec3::new(290.0, 10.0, 40.0)), None);
+        assert_eq!(map.region_at(&Vec3::new(1000.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn first_listed_region_wins() {
+        let map = regions();
+        assert_eq!(map.region_at(&Vec3::new(0.0, 0.0, 50.0)), Some("point"));
+        assert_eq!(map.region_at(&Vec3::new(0.0, 0.0, -50.0)), Some("mid"));
+    }
+
+    #[test]
+    fn points_on_edges_are_inside() {
+        let map = regions();
+        // Bottom edge, and the sloped edges on either side.
+        assert_eq!(map.region_at(&Vec3::new(50.0, -100.0, 0.0)), Some("mid"));
+        assert_eq!(map.region_at(&Vec3::new(50.0, 0.0, 0.0)), Some("mid"));
+        assert_eq!(map.region_at(&Vec3::new(-50.0, 0.0, 0.0)), Some("mid"));
+        // A vertex, and the right hand edge of the other polygon.
+        assert_eq!(map.region_at(&Vec3::new(0.0, 100.0, 0.0)), Some("mid"));
+        assert_eq!(map.region_at(&Vec3::new(300.0, 50.0, 60.0)), Some("upper"));
+    }
+
+    #[test]
+    fn skips_polygons_with_too_few_points() {
+        let map = regions();
+        assert_eq!(map.region_at(&Vec3::new(0.5, 0.5, 500.0)), Some("mid"));
+        assert_eq!(map.region_at(&Vec3::new(1.0, 1.0, 500.0)), Some("mid"));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::parser::{
+    heatmap::MapOverview,
+    summarizer::{MapEvent, MapEventKind, RoundSummary},
+};
+use std::fmt::Write;
+use tf_demo_parser::demo::parser::gamestateanalyser::Team;
+
+const MARKER_SIZE: f32 = 8.0;
+
+fn team_colour(team: Team) -> &'static str {
+    match team {
+        Team::Red => "#b8383b",
+        Team::Blue => "#5885a2",
+        _ => "#a0a0a0",
+    }
+}
+
+// Escape text for use in SVG attributes and elements.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn marker(svg: &mut String, event: &MapEvent, [x, y]: [f32; 2]) {
+    let colour = team_colour(event.team);
+    let s = MARKER_SIZE / 2.0;
+    let shape = match event.kind {
+        MapEventKind::Kill => {
+            format!(r#"<circle cx="{x:.1}" cy="{y:.1}" r="{s:.1}" fill="{colour}"/>"#)
+        }
+        MapEventKind::Death => format!(
+            r#"<path d="M{} {}L{} {}M{} {}L{} {}" stroke="{colour}" stroke-width="2"/>"#,
+            x - s,
+            y - s,
+            x + s,
+            y + s,
+            x - s,
+            y + s,
+            x + s,
+            y - s
+        ),
+        MapEventKind::Uber => format!(
+            r#"<path d="M{x} {}L{} {y}L{x} {}L{} {y}Z" fill="{colour}" stroke="white"/>"#,
+            y - s * 1.5,
+            x + s * 1.5,
+            y + s * 1.5,
+            x - s * 1.5
+        ),
+        MapEventKind::Building => format!(
+            r#"<rect x="{:.1}" y="{:.1}" width="{MARKER_SIZE}" height="{MARKER_SIZE}" fill="none" stroke="{colour}" stroke-width="2"/>"#,
+            x - s,
+            y - s
+        ),
+    };
+
+    let title = match &event.detail {
+        Some(detail) => format!(
+            "{:?} {} ({}) at tick {}",
+            event.kind, event.player, detail, event.tick
+        ),
+        None => format!("{:?} {} at tick {}", event.kind, event.player, event.tick),
+    };
+    let _ = writeln!(svg, "  <g>{shape}<title>{}</title></g>", escape(&title));
+}
+
+/// Draw a round's kills, deaths, uber pops and building placements over a map's overview image,
+/// as a standalone SVG. `image` is used as-is for the image's href, so it can be a path, URL or
+/// data URI. Rounds parsed without `map_events` come out as just the image.
+pub fn render_round_svg(round: &RoundSummary, overview: &MapOverview, image: &str) -> String {
+    let size = overview.size;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"  <image href="{}" x="0" y="0" width="{size}" height="{size}"/>"#,
+        escape(image)
+    );
+
+    // Buildings and deaths underneath, so kills and ubers stay visible where they overlap.
+    for kind in [
+        MapEventKind::Building,
+        MapEventKind::Death,
+        MapEventKind::Kill,
+        MapEventKind::Uber,
+    ] {
+        for event in round.map_events.iter().filter(|e| e.kind == kind) {
+            marker(&mut svg, event, overview.project(&event.origin));
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec3;
+    use tf_demo_parser::demo::data::DemoTick;
+
+    fn overview() -> MapOverview {
+        MapOverview {
+            pos_x: 0.0,
+            pos_y: 1000.0,
+            scale: 1.0,
+            rotation: 0.0,
+            size: 1000.0,
+        }
+    }
+
+    fn event(kind: MapEventKind, team: Team, origin: [f32; 3], detail: Option<&str>) -> MapEvent {
+        MapEvent {
+            tick: DemoTick::from(42),
+            kind,
+            team,
+            player: "<player>".into(),
+            origin: Vec3::from(origin),
+            region: None,
+            detail: detail.map(String::from),
+        }
+    }
+
+    #[test]
+    fn renders_just_the_image_without_events() {
+        let svg = render_round_svg(&RoundSummary::default(), &overview(), "maps/a&b.png");
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(r#"<image href="maps/a&amp;b.png""#));
+        assert!(!svg.contains("<g>"));
+    }
+
+    #[test]
+    fn draws_events_in_layers() {
+        let round = RoundSummary {
+            map_events: vec![
+                event(
+                    MapEventKind::Kill,
+                    Team::Red,
+                    [100.0, 900.0, 0.0],
+                    Some("scattergun"),
+                ),
+                event(MapEventKind::Uber, Team::Blue, [300.0, 700.0, 0.0], None),
+                event(
+                    MapEventKind::Building,
+                    Team::Blue,
+                    [500.0, 500.0, 0.0],
+                    Some("sentry"),
+                ),
+                event(MapEventKind::Death, Team::Blue, [100.0, 900.0, 0.0], None),
+            ],
+            ..Default::default()
+        };
+        let svg = render_round_svg(&round, &overview(), "map.png");
+
+        // Buildings, then deaths, kills and ubers on top.
+        let at = |needle: &str| svg.find(needle).unwrap();
+        let death = r#"<path d="M96 96L104 104M96 104L104 96""#;
+        assert!(at("<rect") < at(death));
+        assert!(at(death) < at("<circle"));
+        assert!(at("<circle") < at(r##"Z" fill="#5885a2""##));
+
+        // World positions are projected onto the overview.
+        assert!(svg.contains(r##"<circle cx="100.0" cy="100.0" r="4.0" fill="#b8383b"/>"##));
+        assert!(svg.contains(r#"<rect x="496.0" y="496.0""#));
+        assert!(svg.contains("<title>Kill &lt;player&gt; (scattergun) at tick 42</title>"));
+        assert!(svg.contains("<title>Uber &lt;player&gt; at tick 42</title>"));
+        assert_eq!(svg.matches("<g>").count(), 4);
+    }
+}
@@ -1,11 +1,45 @@
 use crate::parser::{
     game::{DamageType, Death, RoundState},
-    is_zero,
+    is_zero, is_zero_f32, ordered_map,
+    trajectory::ProjectileTrack,
+    weapon::SentryFire,
 };
 use enumset::EnumSet;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tf_demo_parser::demo::gameevent_gen::PlayerHurtEvent;
 
+/// How a single instance of damage was dealt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DamageKind {
+    #[default]
+    Direct,
+    Afterburn,
+    Bleed,
+}
+
+/// Damage dealt and kills made at a given range.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct RangeStats {
+    #[serde(skip_serializing_if = "is_zero")]
+    pub damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub hits: u32, // counted like accuracy: once per shot or projectile that landed
+    #[serde(skip_serializing_if = "is_zero")]
+    pub kills: u32,
+}
+
+/// Time spent and kills made in a named part of the map.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct RegionStats {
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub time: f32, // in seconds, while alive
+    #[serde(skip_serializing_if = "is_zero")]
+    pub kills: u32, // where the killer stood
+    #[serde(skip_serializing_if = "is_zero")]
+    pub deaths: u32,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Stats {
     #[serde(skip_serializing_if = "is_zero")]
@@ -45,9 +79,29 @@ pub struct Stats {
 
     #[serde(skip_serializing_if = "is_zero")]
     pub damage: u32, // Added up PlayerHurt events
+    // `damage` split by how it was dealt. Afterburn and bleed are credited to the weapon that
+    // applied them rather than whatever the attacker was holding at the time.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub direct_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub afterburn_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub bleed_damage: u32,
     #[serde(skip_serializing_if = "is_zero")]
     pub damage_taken: u32,
 
+    // damage not dealt by another player, and so not part of `damage_taken`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub self_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub fall_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub environment_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub environment_deaths: u32, // pits, trains, drowning...
+    #[serde(skip_serializing_if = "is_zero")]
+    pub environmental_kills: u32, // enemies that died to the environment after we hurt them
+
     #[serde(skip_serializing_if = "is_zero")]
     pub dominations: u32, // This player dominated another player
     #[serde(skip_serializing_if = "is_zero")]
@@ -57,10 +111,21 @@ pub struct Stats {
     #[serde(skip_serializing_if = "is_zero")]
     pub revenged: u32, // Another player got revenge on this player
 
-    // Kills where the victim was in the air for a decent amount of time.
-    // TOOD: clarify this definition
+    #[serde(skip_serializing_if = "is_zero")]
+    pub trades: u32, // killed an enemy shortly after they killed a teammate
+    #[serde(skip_serializing_if = "is_zero")]
+    pub traded_deaths: u32, // died, but a teammate got the killer shortly after
+    #[serde(skip_serializing_if = "is_zero")]
+    pub first_picks: u32, // opening kill of a fight
+    #[serde(skip_serializing_if = "is_zero")]
+    pub first_deaths: u32,
+
+    // Projectile hits and sniper headshots on players well off the ground, see AirshotOptions.
+    // `airshots` only counts the kills.
     #[serde(skip_serializing_if = "is_zero")]
     pub airshots: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub airshot_hits: u32,
 
     #[serde(skip_serializing_if = "is_zero")]
     pub headshot_kills: u32,
@@ -87,10 +152,140 @@ pub struct Stats {
     #[serde(skip_serializing_if = "is_zero")]
     pub hits: u32,
 
+    // projectile stats; `hits` counts each projectile once however many players it damaged
+    #[serde(skip_serializing_if = "is_zero")]
+    pub direct_hits: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub multi_hits: u32, // projectiles that damaged 2+ enemies
+    #[serde(skip_serializing_if = "is_zero")]
+    pub splash_hits: u32, // players caught in splash, not counting direct hits
+    #[serde(skip_serializing_if = "is_zero")]
+    pub splash_damage: u32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub avg_splash_damage: f32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub max_splash_victims: u32,
+
+    // distance between attacker and victim, keyed by range name
+    #[serde(
+        serialize_with = "ordered_map",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub ranges: HashMap<String, RangeStats>,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub avg_kill_distance: f32,
+    #[serde(skip)]
+    pub kill_distance: f32, // total over every kill in `ranges`
+
+    // keyed by region name
+    #[serde(
+        serialize_with = "ordered_map",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub regions: HashMap<String, RegionStats>,
+
+    // blast jumps
+    #[serde(skip_serializing_if = "is_zero")]
+    pub blast_jumps: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub blast_jump_self_damage: u32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub blast_jump_airtime: f32, // in seconds
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub blast_jump_distance: f32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub blast_jump_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub market_gardens: u32, // melee kills while blast jumping
+
+    // movement, while alive
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub distance_travelled: f32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub avg_speed: f32, // units per second
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub max_speed: f32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub airborne_time: f32, // in seconds
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub water_time: f32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub crouched_time: f32,
+    #[serde(skip)]
+    pub alive_time: f32,
+
+    // visibility, sampled while level geometry is loaded
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub time_visible: f32, // in seconds, seen by at least one enemy
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub avg_enemies_in_view: f32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub max_enemies_in_view: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub unseen_kills: u32, // on victims the killer couldn't see shortly before
+    #[serde(skip)]
+    pub visibility_samples: u32,
+    #[serde(skip)]
+    pub enemies_in_view: u32, // total over every sample
+
     #[serde(skip_serializing_if = "is_zero")]
     pub object_built: u32,
     #[serde(skip_serializing_if = "is_zero")]
     pub object_destroyed: u32,
+
+    // engineer support; dispenser numbers are estimated from who stands at one and their health
+    #[serde(skip_serializing_if = "is_zero")]
+    pub teleports_provided: u32, // to teammates
+    #[serde(skip_serializing_if = "is_zero")]
+    pub teleports_taken: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub dispenser_healing: u32, // given to teammates
+    #[serde(skip_serializing_if = "is_zero")]
+    pub dispenser_healing_received: u32,
+    // Seconds teammates spent in range of this player's dispensers, and this player spent in range
+    // of any. STV demos don't include other players' ammo, so ammo given isn't counted.
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub dispenser_visitor_time: f32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub time_at_dispenser: f32,
+
+    // Sentry fire, credited to the engineer. Whatever isn't wrangled was the sentry shooting on
+    // its own, and whatever isn't rockets was bullets.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sentry_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sentry_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub wrangled_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub wrangled_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sentry_rocket_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sentry_rocket_kills: u32,
+    #[serde(skip_serializing_if = "is_zero_f32")]
+    pub wrangler_shield_time: f32, // in seconds
+
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sappers_placed: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sapped_buildings_destroyed: u32, // went down with this player's sapper on them
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sappers_removed: u32,
+
+    // pyro stats
+    #[serde(skip_serializing_if = "is_zero")]
+    pub airblasts: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub reflects: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub reflect_damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub reflect_kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub players_pushed: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub extinguishes: u32,
 }
 
 impl Stats {
@@ -103,6 +298,118 @@ impl Stats {
         self.hits += 1;
     }
 
+    pub fn handle_projectile_hit(&mut self, track: &ProjectileTrack) {
+        self.hits += 1;
+
+        if track.hits.iter().any(|h| h.direct) {
+            self.direct_hits += 1;
+        }
+
+        let victims = track.victims();
+        if victims > 1 {
+            self.multi_hits += 1;
+        }
+        self.max_splash_victims = self.max_splash_victims.max(victims);
+
+        for hit in track.hits.iter().filter(|h| !h.direct) {
+            self.splash_hits += 1;
+            self.splash_damage += hit.damage;
+        }
+        if self.splash_hits > 0 {
+            self.avg_splash_damage = self.splash_damage as f32 / self.splash_hits as f32;
+        }
+    }
+
+    pub fn handle_ranged_damage(&mut self, range: &str, hurt: &PlayerHurtEvent) {
+        self.ranges.entry(range.to_string()).or_default().damage += hurt.damage_amount as u32;
+    }
+
+    pub fn handle_ranged_hit(&mut self, range: &str) {
+        self.ranges.entry(range.to_string()).or_default().hits += 1;
+    }
+
+    pub fn handle_ranged_kill(&mut self, range: &str, distance: f32) {
+        self.ranges.entry(range.to_string()).or_default().kills += 1;
+
+        self.kill_distance += distance;
+        let kills: u32 = self.ranges.values().map(|r| r.kills).sum();
+        self.avg_kill_distance = self.kill_distance / kills as f32;
+    }
+
+    pub fn handle_region_time(&mut self, region: &str, seconds: f32) {
+        self.regions.entry(region.to_string()).or_default().time += seconds;
+    }
+
+    pub fn handle_region_kill(&mut self, region: &str) {
+        self.regions.entry(region.to_string()).or_default().kills += 1;
+    }
+
+    pub fn handle_region_death(&mut self, region: &str) {
+        self.regions.entry(region.to_string()).or_default().deaths += 1;
+    }
+
+    pub fn handle_airshot_hit(&mut self) {
+        self.airshot_hits += 1;
+    }
+
+    pub fn handle_blast_jump(&mut self, airtime: f32, distance: f32) {
+        self.blast_jumps += 1;
+        self.blast_jump_airtime += airtime;
+        self.blast_jump_distance += distance;
+    }
+
+    pub fn handle_movement(&mut self, distance: f32, seconds: f32) {
+        self.distance_travelled += distance;
+        self.max_speed = self.max_speed.max(distance / seconds);
+    }
+
+    pub fn handle_movement_time(
+        &mut self,
+        seconds: f32,
+        airborne: bool,
+        in_water: bool,
+        crouched: bool,
+    ) {
+        self.alive_time += seconds;
+        if airborne {
+            self.airborne_time += seconds;
+        }
+        if in_water {
+            self.water_time += seconds;
+        }
+        if crouched {
+            self.crouched_time += seconds;
+        }
+        if self.alive_time > 0.0 {
+            self.avg_speed = self.distance_travelled / self.alive_time;
+        }
+    }
+
+    pub fn handle_visibility_sample(&mut self, seconds: f32, visible: bool, enemies_in_view: u32) {
+        self.visibility_samples += 1;
+        if visible {
+            self.time_visible += seconds;
+        }
+        self.enemies_in_view += enemies_in_view;
+        self.max_enemies_in_view = self.max_enemies_in_view.max(enemies_in_view);
+        self.avg_enemies_in_view = self.enemies_in_view as f32 / self.visibility_samples as f32;
+    }
+
+    pub fn handle_unseen_kill(&mut self) {
+        self.unseen_kills += 1;
+    }
+
+    pub fn handle_blast_jump_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.blast_jump_self_damage += hurt.damage_amount as u32;
+    }
+
+    pub fn handle_blast_jump_kill(&mut self, market_garden: bool) {
+        self.blast_jump_kills += 1;
+        if market_garden {
+            self.market_gardens += 1;
+        }
+    }
+
     pub fn handle_object_built(&mut self) {
         self.object_built += 1;
     }
@@ -111,6 +418,84 @@ impl Stats {
         self.object_destroyed += 1;
     }
 
+    pub fn handle_teleport_provided(&mut self) {
+        self.teleports_provided += 1;
+    }
+
+    pub fn handle_teleport_taken(&mut self) {
+        self.teleports_taken += 1;
+    }
+
+    pub fn handle_dispenser_supply(&mut self, healing: u32, seconds: f32) {
+        self.dispenser_healing += healing;
+        self.dispenser_visitor_time += seconds;
+    }
+
+    pub fn handle_dispenser_visit(&mut self, healing: u32, seconds: f32) {
+        self.dispenser_healing_received += healing;
+        self.time_at_dispenser += seconds;
+    }
+
+    pub fn handle_sentry_damage(&mut self, damage: u32, fire: SentryFire) {
+        self.sentry_damage += damage;
+        if fire.wrangled {
+            self.wrangled_damage += damage;
+        }
+        if fire.rocket {
+            self.sentry_rocket_damage += damage;
+        }
+    }
+
+    pub fn handle_sentry_kill(&mut self, fire: SentryFire) {
+        self.sentry_kills += 1;
+        if fire.wrangled {
+            self.wrangled_kills += 1;
+        }
+        if fire.rocket {
+            self.sentry_rocket_kills += 1;
+        }
+    }
+
+    pub fn handle_wrangler_shield(&mut self, seconds: f32) {
+        self.wrangler_shield_time += seconds;
+    }
+
+    pub fn handle_sapper_placed(&mut self) {
+        self.sappers_placed += 1;
+    }
+
+    pub fn handle_sapped_building_destroyed(&mut self) {
+        self.sapped_buildings_destroyed += 1;
+    }
+
+    pub fn handle_sapper_removed(&mut self) {
+        self.sappers_removed += 1;
+    }
+
+    pub fn handle_airblast(&mut self) {
+        self.airblasts += 1;
+    }
+
+    pub fn handle_reflect(&mut self) {
+        self.reflects += 1;
+    }
+
+    pub fn handle_reflect_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.reflect_damage += hurt.damage_amount as u32;
+    }
+
+    pub fn handle_reflect_kill(&mut self) {
+        self.reflect_kills += 1;
+    }
+
+    pub fn handle_push(&mut self) {
+        self.players_pushed += 1;
+    }
+
+    pub fn handle_extinguish(&mut self) {
+        self.extinguishes += 1;
+    }
+
     pub fn handle_drop(&mut self) {
         self.drops += 1;
     }
@@ -129,8 +514,19 @@ impl Stats {
         self.charges_quickfix += 1;
     }
 
-    pub fn handle_damage_dealt(&mut self, hurt: &PlayerHurtEvent, damage_type: DamageType) {
-        self.damage += hurt.damage_amount as u32;
+    pub fn handle_damage_dealt(
+        &mut self,
+        hurt: &PlayerHurtEvent,
+        damage_type: DamageType,
+        kind: DamageKind,
+    ) {
+        let amount = hurt.damage_amount as u32;
+        self.damage += amount;
+        match kind {
+            DamageKind::Direct => self.direct_damage += amount,
+            DamageKind::Afterburn => self.afterburn_damage += amount,
+            DamageKind::Bleed => self.bleed_damage += amount,
+        }
 
         if damage_type == DamageType::Backstab {
             self.backstabs += 1;
@@ -149,6 +545,42 @@ impl Stats {
         }
     }
 
+    pub fn handle_self_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.self_damage += hurt.damage_amount as u32;
+    }
+
+    pub fn handle_fall_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.fall_damage += hurt.damage_amount as u32;
+    }
+
+    pub fn handle_environment_damage(&mut self, hurt: &PlayerHurtEvent) {
+        self.environment_damage += hurt.damage_amount as u32;
+    }
+
+    pub fn handle_environment_death(&mut self) {
+        self.environment_deaths += 1;
+    }
+
+    pub fn handle_environmental_kill(&mut self) {
+        self.environmental_kills += 1;
+    }
+
+    pub fn handle_trade(&mut self) {
+        self.trades += 1;
+    }
+
+    pub fn handle_traded_death(&mut self) {
+        self.traded_deaths += 1;
+    }
+
+    pub fn handle_first_pick(&mut self) {
+        self.first_picks += 1;
+    }
+
+    pub fn handle_first_death(&mut self) {
+        self.first_deaths += 1;
+    }
+
     pub fn handle_death(&mut self, round_state: RoundState, flags: EnumSet<Death>) {
         if flags.contains(Death::Domination) {
             self.dominated += 1;
@@ -247,3 +679,53 @@ impl Stats {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::trajectory::ProjectileHit;
+
+    fn hit(victim: &str, damage: u32, direct: bool) -> ProjectileHit {
+        ProjectileHit {
+            victim: victim.into(),
+            damage,
+            direct,
+            distance: 0.0,
+        }
+    }
+
+    fn rocket(hits: Vec<ProjectileHit>) -> ProjectileTrack {
+        ProjectileTrack {
+            hits,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn projectile_hits_split_direct_and_splash() {
+        let mut stats = Stats::default();
+        stats.handle_projectile_hit(&rocket(vec![hit("a", 90, true), hit("b", 40, false)]));
+        stats.handle_projectile_hit(&rocket(vec![hit("c", 20, false)]));
+
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.direct_hits, 1);
+        assert_eq!(stats.splash_hits, 2);
+        assert_eq!(stats.splash_damage, 60);
+        assert_eq!(stats.avg_splash_damage, 30.0);
+    }
+
+    #[test]
+    fn multi_hits_count_distinct_victims() {
+        let mut stats = Stats::default();
+        // Hurting the same player twice with one projectile is still a single victim.
+        stats.handle_projectile_hit(&rocket(vec![hit("a", 50, true), hit("a", 10, false)]));
+        assert_eq!(stats.multi_hits, 0);
+        assert_eq!(stats.max_splash_victims, 1);
+
+        let three = ["a", "b", "c"].map(|v| hit(v, 30, false));
+        stats.handle_projectile_hit(&rocket(three.into()));
+        stats.handle_projectile_hit(&rocket(vec![hit("a", 30, false), hit("b", 30, false)]));
+        assert_eq!(stats.multi_hits, 2);
+        assert_eq!(stats.max_splash_victims, 3);
+    }
+}
@@ -1,14 +1,30 @@
 use crate::{
     Vec3,
     parser::{
-        entity::{self, Entity, ProjectileType},
+        bsp,
+        building::{
+            self, BuildingRecord, BuildingState, BuildingTracker, SapTarget, SapperRecord,
+            SapperTracker,
+        },
+        entity::{self, Entity, EntityClass, ProjectileType},
+        fight::{Combatant, Fight, FightTracker},
         game::{
-            Damage, DamageEffect, DamageType, Death, INVALID_HANDLE, PlayerAnimation, RoundState,
-            WeaponId,
+            Damage, DamageEffect, DamageType, Death, INVALID_HANDLE, PlayerAnimation,
+            PlayerCondition, RoundState, WeaponId, handle_entity,
         },
-        is_false,
+        heatmap::{Engagement, EngagementKind, Heatmaps},
+        history::{InteractionHistory, InteractionKind},
+        is_false, is_zero,
+        options::ParseOptions,
         player::PlayerSummary,
+        positions::{ObjectTrack, PositionTracks},
         props::*,
+        recap::{CritType, DamageInstance, DeathRecap},
+        region::RegionMap,
+        sample_due,
+        stats::DamageKind,
+        trajectory::{self, ProjectileHit, ProjectileTrack},
+        visibility::{self, Viewer},
         weapon::{self, projectile_log_name, sentry_name, taunt_log_name},
     },
     schema::{Item, Schema},
@@ -16,14 +32,13 @@ use crate::{
 use alga::linear::EuclideanSpace;
 use enumset::EnumSet;
 use num_enum::TryFromPrimitive;
-use parry3d::math::Vector;
 use rapier3d::prelude::{
-    ColliderBuilder, ColliderHandle, ColliderSet, Cuboid, IslandManager, QueryPipeline,
-    RigidBodySet,
+    ColliderBuilder, ColliderHandle, ColliderSet, Group, InteractionGroups, IslandManager,
+    QueryPipeline, RigidBodySet,
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::Arc,
 };
 use tf_demo_parser::{
@@ -31,8 +46,8 @@ use tf_demo_parser::{
     demo::{
         data::{DemoTick, MaybeUtf8String, UserInfo},
         gameevent_gen::{
-            PlayerDeathEvent, PlayerHurtEvent, TeamPlayCaptureBlockedEvent,
-            TeamPlayPointCapturedEvent,
+            PlayerDeathEvent, PlayerExtinguishedEvent, PlayerHurtEvent,
+            TeamPlayCaptureBlockedEvent, TeamPlayPointCapturedEvent,
         },
         gamevent::GameEvent,
         message::{
@@ -58,6 +73,10 @@ use tracing::{debug, error, span::EnteredSpan, trace, warn};
 pub struct DemoSummary {
     pub rounds: Vec<RoundSummary>,
     pub chat: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub positions: Option<PositionTracks>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heatmaps: Option<Heatmaps>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -80,6 +99,75 @@ pub struct PlayerDeath {}
 
 const ENTITY_COUNT: usize = 2048;
 
+// Collision groups, so queries can pick between entities and the level itself.
+pub const ENTITY_GROUP: Group = Group::GROUP_1;
+pub const WORLD_GROUP: Group = Group::GROUP_2;
+
+// Seconds between afterburn hits, and ticks of leeway for when they're seen.
+const AFTERBURN_PERIOD: f32 = 0.5;
+const AFTERBURN_SLACK: u32 = 3;
+
+// How far from a teleporter exit a player can be when they are seen to have just used it.
+const TELEPORT_RANGE: f32 = 64.0;
+
+// A dispenser's trigger box around its origin: 70 units either side and 50 up.
+const DISPENSER_REACH: f32 = 70.0;
+const DISPENSER_HEIGHT: f32 = 50.0;
+
+// Health per second given out at each dispenser level.
+const DISPENSER_HEAL_RATES: [u32; 3] = [10, 15, 20];
+
+// object_destroyed's objecttype for a sapper, rather than a building.
+const OBJECT_TYPE_SAPPER: u16 = 3;
+
+// How far from a sapped robot its sapper can be, for when the sapper doesn't say what it's on.
+const ROBOT_SAPPER_RANGE: f32 = 100.0;
+
+// Direct flamethrower damage and afterburn share a damage type and weapon. Afterburn never crits
+// and lands on a fixed cadence, so flames hitting in between are direct.
+fn is_afterburn(
+    victim: &PlayerSummary,
+    hurt: &PlayerHurtEvent,
+    damage_type: DamageType,
+    weapon: &WeaponId,
+    tick: DemoTick,
+    period: u32,
+) -> bool {
+    damage_type == DamageType::Burning
+        && (*weapon != WeaponId::Flamethrower
+            || (!hurt.crit
+                && !hurt.mini_crit
+                && victim.afterburn_due(tick, period, AFTERBURN_SLACK)))
+}
+
+// Killing with a melee weapon while blast jumping.
+fn is_market_garden(blast_jumping: bool, weapon_id: u16) -> bool {
+    blast_jumping && WeaponId::try_from(weapon_id).is_ok_and(|w| w.is_melee())
+}
+
+// The exit a player that just teleported to `origin` came out of: the closest one in range that
+// isn't being carried.
+fn teleporter_exit<'a>(
+    teleporters: impl IntoIterator<Item = &'a entity::Teleporter>,
+    origin: &Vec3,
+) -> Option<&'a entity::Teleporter> {
+    teleporters
+        .into_iter()
+        .filter(|t| t.exit && !t.carried)
+        .map(|t| (t, EuclideanSpace::distance(&t.origin, origin)))
+        .filter(|(_, distance)| *distance <= TELEPORT_RANGE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(t, _)| t)
+}
+
+// How much of a player's health going from `before` to `health` a dispenser at `level` could
+// have given them over `seconds`.
+fn dispenser_healing(level: u32, seconds: f32, before: u32, health: u32) -> u32 {
+    let rate = DISPENSER_HEAL_RATES[(level.clamp(1, 3) - 1) as usize];
+    let max_healing = (rate as f32 * seconds).round() as u32;
+    health.saturating_sub(before).min(max_healing)
+}
+
 #[derive(Clone, Debug)]
 pub struct Explosion {
     pub projectile: Box<entity::Projectile>,
@@ -88,6 +176,7 @@ pub struct Explosion {
 
 #[derive(Clone, Debug)]
 pub struct SentryShot {
+    pub id: EntityId,
     pub sentry: entity::Sentry,
 }
 
@@ -106,6 +195,8 @@ pub struct Hurt {
     pub wep: u32,
     pub origin: Vec3,
     pub source: HurtSource,
+    pub kind: DamageKind,
+    pub airshot: bool,
 }
 
 pub struct MatchAnalyzer<'a> {
@@ -128,8 +219,10 @@ pub struct MatchAnalyzer<'a> {
     span: Option<EnteredSpan>,
     tick: DemoTick,
     server_tick: u32,
+    interval_per_tick: f32,
     tick_events: Vec<Event>,
     schema: &'a Schema,
+    options: ParseOptions,
 
     // Events that happened this tick
     hurts: Vec<Hurt>,
@@ -138,6 +231,21 @@ pub struct MatchAnalyzer<'a> {
     deleted_entities: HashSet<EntityId>,
 
     airblasts: HashSet<u32>, // handles of players that airblasted this tick
+    flame_hits: HashSet<(UserId, UserId)>, // attacker and victim of each flame hit this tick
+    pending_pushes: Vec<PendingPush>,
+
+    projectile_tracks: HashMap<EntityId, ProjectileTrack>, // projectiles still in flight
+    fight: FightTracker,
+    buildings: BuildingTracker,
+    sappers: SapperTracker,
+    positions: Option<PositionTracks>,
+    engagements: Option<Vec<Engagement>>, // for heatmaps
+    map: String,
+    regions: Option<RegionMap>,             // callouts for the current map
+    region_tick: Option<DemoTick>,          // up to which time spent in regions has been counted
+    dispenser_health: HashMap<String, u32>, // health of players at a dispenser, last check
+    visibility_sample: Option<DemoTick>,
+    dispenser_sample: Option<DemoTick>,
 
     // Queryable geometry world. QVBH under the hood.
     world: QueryPipeline,
@@ -146,6 +254,7 @@ pub struct MatchAnalyzer<'a> {
     rigid_body_set: RigidBodySet, // unused, but needed for some APIs :\
     mutated_colliders: Vec<ColliderHandle>,
     removed_colliders: Vec<ColliderHandle>,
+    world_collider: Option<ColliderHandle>, // level geometry, if the map could be loaded
 
     weapon_class_ids: HashSet<ClassId>,
     projectile_class_ids: HashSet<ClassId>,
@@ -240,6 +349,78 @@ impl MatchAnalyzerView<'_> {
 
         p.handle_object_built(weapon::weapon_name(item, class));
     }
+
+    pub fn handle_projectile_reflected(&mut self, pyro: &u32, kind: entity::ProjectileType) {
+        let Some(eid) = self.entity_handles.get(pyro) else {
+            error!("Could not find player entity for handle that reflected projectile {pyro:?}");
+            return;
+        };
+        let Some(pe) = self.get_player(eid) else {
+            error!("Could not find player entity that reflected projectile {pyro:?}");
+            return;
+        };
+
+        let class = pe.class;
+        let uid = pe.user_id;
+
+        let Some(item) = self
+            .entity_handles
+            .get(&pe.last_active_weapon_handle)
+            .and_then(|eid| {
+                self.entities
+                    .get(usize::from(*eid))
+                    .and_then(|b| b.as_ref())
+            })
+            .and_then(|e| e.weapon())
+            .and_then(|w| self.schema.items.get(&w.schema_id))
+        else {
+            error!("Could not find item used to reflect projectile");
+            return;
+        };
+
+        let Some(steamid) = self.user_id_to_steam_id.get(&uid).cloned() else {
+            error!("Could not find steamid for user {uid} that reflected projectile");
+            return;
+        };
+        let Some(p) = self.player_summaries.get_mut(&steamid) else {
+            error!("Could not find player summary for steamid {steamid} that reflected projectile");
+            return;
+        };
+
+        p.handle_reflect(weapon::weapon_name(item, class), kind);
+    }
+}
+
+// A player that was in front of a pyro when they airblasted; credited as a push if their velocity
+// jumps away from the pyro within a few ticks.
+#[derive(Debug)]
+pub struct PendingPush {
+    pub pyro: UserId,
+    pub weapon: &'static str,
+    pub origin: Vec3, // of the pyro
+    pub victim: EntityId,
+    pub velocity: Vec3, // of the victim
+    pub tick: DemoTick,
+}
+
+impl PendingPush {
+    // Whether a player at `target` is caught by an airblast from `origin` facing `yaw` degrees.
+    pub fn in_reach(origin: Vec3, yaw: f32, target: Vec3) -> bool {
+        let (yaw_sin, yaw_cos) = yaw.to_radians().sin_cos();
+        let offset = target - origin;
+        offset.norm() <= weapon::AIRBLAST_RANGE && offset.x * yaw_cos + offset.y * yaw_sin > 0.0
+    }
+
+    // Whether the victim, now at `origin` moving at `velocity`, has been knocked away from the pyro.
+    pub fn pushed(&self, origin: Vec3, velocity: Vec3) -> bool {
+        let change = velocity - self.velocity;
+        change.norm() > weapon::AIRBLAST_PUSH_SPEED && change.dot(&(origin - self.origin)) > 0.0
+    }
+
+    // Whether the airblast can still be credited at `tick`.
+    pub fn pending(&self, tick: DemoTick) -> bool {
+        u32::from(tick) - u32::from(self.tick) < weapon::AIRBLAST_PUSH_WINDOW
+    }
 }
 
 #[derive(Debug)]
@@ -247,6 +428,7 @@ pub enum Event {
     Death(Box<PlayerDeathEvent>),
     Hurt(PlayerHurtEvent),
     MedigunCharged(u32),
+    Teleported(UserId),
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
@@ -256,6 +438,73 @@ pub struct Killstreak {
     pub duration: u32,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Airshot {
+    pub tick: DemoTick,
+    pub attacker: String, // steamid
+    pub victim: String,   // steamid
+    pub weapon: String,
+    pub height: f32, // above where the victim last stood
+    pub distance: f32,
+    #[serde(skip_serializing_if = "is_false")]
+    pub kill: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EnvironmentCause {
+    TriggerHurt, // pits and other kill zones
+    Fall,
+    Crush, // trains, doors
+    Drown,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnvironmentDeath {
+    pub tick: DemoTick,
+    pub victim: String, // steamid
+    pub cause: EnvironmentCause,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credited: Option<String>, // steamid of the enemy credited with the kill
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weapon: Option<String>, // that last hurt or pushed the victim
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via: Option<InteractionKind>,
+}
+
+/// What one player (or class) did to another over a round.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Matchup {
+    #[serde(skip_serializing_if = "is_zero")]
+    pub damage: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub kills: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub hits: u32, // counted like accuracy: once per shot or projectile that landed
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MapEventKind {
+    Kill,
+    Death,
+    Uber,
+    Building,
+}
+
+/// Something worth marking on a minimap.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MapEvent {
+    pub tick: DemoTick,
+    pub kind: MapEventKind,
+    pub team: Team,
+    pub player: String, // steamid
+    pub origin: Vec3,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>, // weapon, or type of building
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct RoundSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -274,12 +523,113 @@ pub struct RoundSummary {
     pub winners: Vec<String>, // steamids
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub losers: Vec<String>, // steamids
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub airshots: Vec<Airshot>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub environment_deaths: Vec<EnvironmentDeath>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deaths: Vec<DeathRecap>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fights: Vec<Fight>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub map_events: Vec<MapEvent>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub buildings: Vec<BuildingRecord>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sappers: Vec<SapperRecord>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub projectiles: Vec<ProjectileTrack>,
+
+    // attacker steamid -> victim steamid
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub damage_matrix: BTreeMap<String, BTreeMap<String, Matchup>>,
+    // attacker class -> victim class
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub class_matrix: BTreeMap<String, BTreeMap<String, Matchup>>,
+}
+
+impl RoundSummary {
+    fn matchups(
+        &mut self,
+        attacker: &str,
+        victim: &str,
+        attacker_class: Class,
+        victim_class: Class,
+    ) -> [&mut Matchup; 2] {
+        [
+            self.damage_matrix
+                .entry(attacker.to_string())
+                .or_default()
+                .entry(victim.to_string())
+                .or_default(),
+            self.class_matrix
+                .entry(attacker_class.to_string())
+                .or_default()
+                .entry(victim_class.to_string())
+                .or_default(),
+        ]
+    }
+
+    pub fn handle_matchup_damage(
+        &mut self,
+        attacker: &str,
+        victim: &str,
+        attacker_class: Class,
+        victim_class: Class,
+        damage: u32,
+    ) {
+        for m in self.matchups(attacker, victim, attacker_class, victim_class) {
+            m.damage += damage;
+        }
+    }
+
+    pub fn handle_matchup_hit(
+        &mut self,
+        attacker: &str,
+        victim: &str,
+        attacker_class: Class,
+        victim_class: Class,
+    ) {
+        for m in self.matchups(attacker, victim, attacker_class, victim_class) {
+            m.hits += 1;
+        }
+    }
+
+    pub fn handle_matchup_kill(
+        &mut self,
+        attacker: &str,
+        victim: &str,
+        attacker_class: Class,
+        victim_class: Class,
+    ) {
+        for m in self.matchups(attacker, victim, attacker_class, victim_class) {
+            m.kills += 1;
+        }
+    }
 }
 
 impl<'a> MatchAnalyzer<'a> {
     pub fn new(schema: &'a Schema) -> Self {
+        Self::with_options(schema, ParseOptions::default())
+    }
+
+    pub fn with_options(schema: &'a Schema, options: ParseOptions) -> Self {
+        let positions = options
+            .positions
+            .enabled
+            .then(|| PositionTracks::new(options.positions.interval));
+        let engagements = options.heatmaps.enabled.then(Vec::new);
         Self {
             schema,
+            options,
             chat: Default::default(),
             current_round: Default::default(),
             rounds: Default::default(),
@@ -298,11 +648,26 @@ impl<'a> MatchAnalyzer<'a> {
             span: Default::default(),
             tick: Default::default(),
             server_tick: Default::default(),
+            interval_per_tick: Default::default(),
             tick_events: Default::default(),
             hurts: Default::default(),
             sentry_shots: Default::default(),
             explosions: Default::default(),
             airblasts: Default::default(),
+            flame_hits: Default::default(),
+            pending_pushes: Default::default(),
+            projectile_tracks: Default::default(),
+            fight: Default::default(),
+            buildings: Default::default(),
+            sappers: Default::default(),
+            positions,
+            engagements,
+            map: Default::default(),
+            regions: None,
+            region_tick: None,
+            dispenser_health: Default::default(),
+            visibility_sample: None,
+            dispenser_sample: None,
             deleted_entities: Default::default(),
             world: QueryPipeline::new(),
             island_manager: IslandManager::new(),
@@ -310,6 +675,7 @@ impl<'a> MatchAnalyzer<'a> {
             rigid_body_set: RigidBodySet::with_capacity(0),
             mutated_colliders: Vec::with_capacity(ENTITY_COUNT),
             removed_colliders: Vec::with_capacity(ENTITY_COUNT),
+            world_collider: None,
             projectile_class_ids: Default::default(),
             weapon_class_ids: Default::default(),
         }
@@ -485,10 +851,15 @@ impl<'a> MatchAnalyzer<'a> {
         } else if damage_type == DamageType::DragonsFuryBonusBurning {
             my_name = "dragons_fury_bonus";
         } else if damage_type == DamageType::Burning {
-            my_name = self
-                .get_weapon(&attacker.weapon_handles[0])
-                .and_then(|w| self.schema.items.get(&w.schema_id))
-                .and_then(|i| i.item_logname.as_ref().map(|s| ustr::ustr(s).as_str()))
+            my_name = dmg_to_victim
+                .iter()
+                .find(|h| h.attacker == attacker.user_id && h.kind == DamageKind::Afterburn)
+                .and_then(|h| self.dot_source(h))
+                .or_else(|| {
+                    self.get_weapon(&attacker.weapon_handles[0])
+                        .and_then(|w| self.schema.items.get(&w.schema_id))
+                        .and_then(|i| i.item_logname.as_ref().map(|s| ustr::ustr(s).as_str()))
+                })
                 .unwrap_or("flamethrower");
         } else if damage_type == DamageType::BurningArrow {
             my_name = self
@@ -516,8 +887,6 @@ impl<'a> MatchAnalyzer<'a> {
             } else {
                 error!("Chart impact without a shield?!")
             }
-        } else if damage_type == DamageType::PlayerSentry {
-            my_name = "wrangler_kill";
         } else if damage_type == DamageType::Baseball {
             my_name = "ball";
         } else if damage_type == DamageType::ComboPunch {
@@ -539,8 +908,13 @@ impl<'a> MatchAnalyzer<'a> {
             my_name = "sticky_resistance";
         } else if damage_type == DamageType::StickbombExplosion {
             my_name = "ullapool_caber_explosion";
+        } else if damage_type == DamageType::SapperRecorderDeath {
+            my_name = "recorder";
         } else if damage_type == DamageType::Bleeding {
-            my_name = "bleed_kill";
+            // Kills keep TF2's log name, but the damage itself goes to whatever caused the bleed.
+            my_name = hurt
+                .and_then(|h| self.dot_source(h))
+                .unwrap_or("bleed_kill");
         } else if dmg_to_victim.is_empty() || damage_type == DamageType::Suicide {
             if dmg_to_victim.is_empty() && damage_type != DamageType::Suicide {
                 error!("No hurts for non-suicide???");
@@ -555,9 +929,473 @@ impl<'a> MatchAnalyzer<'a> {
                 "world"
             };
         }
+
+        // Logs name all wrangled fire after the wrangler, rockets included.
+        if damage_type == DamageType::PlayerSentry {
+            my_name = "wrangler_kill";
+        }
         my_name
     }
 
+    // Converts a duration in seconds to demo ticks.
+    fn ticks(&self, seconds: f32) -> u32 {
+        (seconds / self.interval_per_tick) as u32
+    }
+
+    // Add the map's level geometry to the world, if there's a local copy of it.
+    fn load_geometry(&mut self) {
+        let Some(dir) = &self.options.geometry.maps_dir else {
+            return;
+        };
+        // The map name comes from the demo, so don't let it reach outside the maps directory.
+        if self.map.is_empty() || self.map.contains(['/', '\\', ':']) || self.map.contains("..") {
+            error!(
+                "Not loading map geometry for suspicious map name {:?}",
+                self.map
+            );
+            return;
+        }
+        let path = dir.join(format!("{}.bsp", self.map));
+        let geometry = match bsp::load(&path) {
+            Ok(geometry) => geometry,
+            Err(e) => {
+                error!("Could not load map geometry from {}: {e}", path.display());
+                return;
+            }
+        };
+        let builder = match ColliderBuilder::trimesh(geometry.vertices, geometry.indices) {
+            Ok(builder) => builder,
+            Err(e) => {
+                error!("Invalid map geometry in {}: {e:?}", path.display());
+                return;
+            }
+        };
+
+        let mut removed = vec![];
+        if let Some(old) = self.world_collider.take() {
+            self.collider_set.remove(
+                old,
+                &mut self.island_manager,
+                &mut self.rigid_body_set,
+                false,
+            );
+            removed.push(old);
+        }
+        let c = builder
+            .collision_groups(InteractionGroups::new(WORLD_GROUP, Group::ALL))
+            .build();
+        let k = self.collider_set.insert(c);
+        self.world_collider = Some(k);
+        // Not left for the next PacketEntities message, which starts by clearing these lists.
+        self.world
+            .update_incremental(&self.collider_set, &[k], &removed, true);
+    }
+
+    fn sample_visibility(&mut self) {
+        if self.world_collider.is_none() {
+            return;
+        }
+        let interval = self.options.visibility.interval;
+        let Some(elapsed) = sample_due(&mut self.visibility_sample, self.tick, interval) else {
+            return;
+        };
+
+        let mut viewers = vec![];
+        for p in self.player_summaries.values() {
+            let Some(e) = self.get_player(&p.entity_id) else {
+                continue;
+            };
+            if e.health == 0 || (e.team != Team::Red && e.team != Team::Blue) {
+                continue;
+            }
+            viewers.push(Viewer::new(p.steamid.clone(), e));
+        }
+
+        let sightings = visibility::sightings(
+            &self.world,
+            &self.collider_set,
+            &self.rigid_body_set,
+            &viewers,
+            self.options.visibility.fov,
+        );
+        let mut in_view = vec![vec![]; viewers.len()];
+        let mut visible = vec![false; viewers.len()];
+        for (v, t) in sightings {
+            in_view[v].push(viewers[t].steamid.as_str());
+            visible[t] = true;
+        }
+
+        let seconds = elapsed as f32 * self.interval_per_tick;
+        for p in self.player_summaries.values_mut() {
+            let Some(i) = viewers.iter().position(|v| v.steamid == p.steamid) else {
+                // Dead players don't see anyone.
+                p.in_view_since.clear();
+                continue;
+            };
+            p.handle_visibility_sample(self.tick, seconds, visible[i], &in_view[i]);
+        }
+    }
+
+    // The sentry behind whatever did sentry damage: the sentry itself, or a rocket it fired.
+    fn sentry_fired(&self, inflictor: EntityId) -> Option<EntityId> {
+        let rocket = self
+            .get_entity(inflictor)
+            .and_then(|e| e.projectile())
+            .or_else(|| {
+                self.explosions
+                    .iter()
+                    .map(|e| e.projectile.as_ref())
+                    .find(|p| p.id == inflictor)
+            });
+        match rocket {
+            Some(rocket) => rocket.sentry,
+            None => Some(inflictor),
+        }
+    }
+
+    // The steamid and team of whoever built an object, from its builder handle.
+    fn builder(&self, owner: u32) -> Option<(String, Team)> {
+        let builder = self
+            .entity_handles
+            .get(&owner)
+            .and_then(|eid| self.get_player(eid))?;
+        let steamid = self.user_id_to_steam_id.get(&builder.user_id)?;
+        Some((steamid.clone(), builder.team))
+    }
+
+    // Credits the player and whoever built the exit they came out of. Spies can use enemy
+    // teleporters, so the exit's team doesn't matter.
+    fn handle_teleported(&mut self, user_id: UserId) {
+        let Some(steamid) = self.user_id_to_steam_id.get(&user_id).cloned() else {
+            error!("No steamid for teleported user {user_id}");
+            return;
+        };
+        let Some(origin) = self
+            .player_summaries
+            .get(&steamid)
+            .and_then(|p| self.get_player(&p.entity_id))
+            .map(|p| p.origin)
+        else {
+            error!("No player entity for teleported player {steamid}");
+            return;
+        };
+
+        let teleporters = self
+            .entities
+            .iter()
+            .flatten()
+            .filter_map(|e| e.teleporter());
+        let Some(exit) = teleporter_exit(teleporters, &origin) else {
+            // Engineers can also teleport home with the Eureka Effect.
+            trace!("Could not find the teleporter exit {steamid} came out of");
+            return;
+        };
+
+        let builder = self.builder(exit.owner).map(|(builder, _)| builder);
+        if builder.is_none() {
+            error!("Could not find who built the teleporter {steamid} came out of");
+        }
+        self.credit_teleport(&steamid, builder.as_deref());
+    }
+
+    fn credit_teleport(&mut self, steamid: &str, builder: Option<&str>) {
+        if let Some(p) = self.player_summaries.get_mut(steamid) {
+            p.handle_teleport_taken();
+        }
+        // Engineers taking their own teleporter isn't a teleport for the scoreboard either.
+        if let Some(builder) = builder
+            && builder != steamid
+            && let Some(p) = self.player_summaries.get_mut(builder)
+        {
+            p.handle_teleport_provided();
+        }
+    }
+
+    // Estimates what dispensers gave out by who stood in range of one, and how much their health
+    // went up while there, capped at what the dispenser could have healed.
+    fn sample_dispensers(&mut self) {
+        let interval = self.options.dispensers.interval;
+        let Some(elapsed) = sample_due(&mut self.dispenser_sample, self.tick, interval) else {
+            return;
+        };
+        let seconds = elapsed as f32 * self.interval_per_tick;
+
+        let mut dispensers = vec![];
+        for d in self.entities.iter().flatten().filter_map(|e| e.dispenser()) {
+            if d.carried {
+                continue;
+            }
+            if let Some((builder, team)) = self.builder(d.owner) {
+                dispensers.push((d, builder, team));
+            }
+        }
+
+        let mut visits = vec![];
+        for p in self.player_summaries.values() {
+            let Some(e) = self.get_player(&p.entity_id) else {
+                continue;
+            };
+            if e.health == 0 {
+                continue;
+            }
+            let at = dispensers
+                .iter()
+                .filter(|(d, _, team)| {
+                    let offset = e.origin - d.origin;
+                    *team == e.team
+                        && offset.x.abs() <= DISPENSER_REACH
+                        && offset.y.abs() <= DISPENSER_REACH
+                        && (0.0..=DISPENSER_HEIGHT).contains(&offset.z)
+                })
+                .min_by(|a, b| {
+                    let da = EuclideanSpace::distance(&a.0.origin, &e.origin);
+                    let db = EuclideanSpace::distance(&b.0.origin, &e.origin);
+                    da.total_cmp(&db)
+                });
+            let Some((d, builder, _)) = at else {
+                continue;
+            };
+
+            let healing = self.dispenser_health.get(&p.steamid).map_or(0, |before| {
+                dispenser_healing(d.level, seconds, *before, e.health)
+            });
+            visits.push((p.steamid.clone(), builder.clone(), healing, e.health));
+        }
+
+        self.dispenser_health.clear();
+        for (steamid, builder, healing, health) in visits {
+            self.dispenser_health.insert(steamid.clone(), health);
+            if let Some(p) = self.player_summaries.get_mut(&steamid) {
+                p.handle_dispenser_visit(healing, seconds);
+            }
+            if builder != steamid
+                && let Some(p) = self.player_summaries.get_mut(&builder)
+            {
+                p.handle_dispenser_supply(healing, seconds);
+            }
+        }
+    }
+
+    fn region_at(&self, pos: &Vec3) -> Option<String> {
+        self.regions
+            .as_ref()
+            .and_then(|r| r.region_at(pos))
+            .map(String::from)
+    }
+
+    // Credit every living player with the time since the last call in whichever region they're in.
+    // The first call only starts the clock.
+    fn track_region_time(&mut self) {
+        let Some(last) = self.region_tick.replace(self.tick) else {
+            return;
+        };
+        let elapsed = u32::from(self.tick).saturating_sub(u32::from(last));
+        let Some(regions) = &self.regions else {
+            return;
+        };
+        let seconds = elapsed as f32 * self.interval_per_tick;
+
+        let mut times = vec![];
+        for p in self.player_summaries.values() {
+            let Some(e) = self.get_player(&p.entity_id) else {
+                continue;
+            };
+            if e.health == 0 || (e.team != Team::Red && e.team != Team::Blue) {
+                continue;
+            }
+            if let Some(region) = regions.region_at(&e.origin) {
+                times.push((p.steamid.clone(), region.to_string()));
+            }
+        }
+        for (steamid, region) in times {
+            if let Some(p) = self.player_summaries.get_mut(&steamid) {
+                p.handle_region_time(&region, seconds);
+            }
+        }
+    }
+
+    fn primary_weapon_name(&self, player: &entity::Player) -> Option<&'static str> {
+        self.get_weapon(&player.weapon_handles[0])
+            .and_then(|w| self.schema.items.get(&w.schema_id))
+            .map(|item| weapon::weapon_name(item, player.class))
+    }
+
+    fn handle_airblast(&mut self, handle: &u32) {
+        let Some(pyro) = self
+            .entity_handles
+            .get(handle)
+            .and_then(|eid| self.get_player(eid))
+        else {
+            error!("Could not find player entity for airblast {handle}");
+            return;
+        };
+        let Some(weapon) = self.primary_weapon_name(pyro) else {
+            error!("Could not find weapon used to airblast {handle}");
+            return;
+        };
+        let Some(summary) = self.get_player_summary_mut_handle(handle) else {
+            error!("Could not find player summary for airblast {handle}");
+            return;
+        };
+        summary.handle_airblast(weapon);
+    }
+
+    // Remember who was in front of each pyro that airblasted this tick, and credit pushes for
+    // earlier airblasts whose victims have since been knocked away.
+    fn track_airblast_pushes(&mut self) {
+        let mut pushes = vec![];
+        for handle in &self.airblasts {
+            let Some(pyro) = self
+                .entity_handles
+                .get(handle)
+                .and_then(|eid| self.get_player(eid))
+            else {
+                continue;
+            };
+            let Some(weapon) = self.primary_weapon_name(pyro) else {
+                continue;
+            };
+
+            for summary in self.player_summaries.values() {
+                let Some(victim) = self.get_player(&summary.entity_id) else {
+                    continue;
+                };
+                if victim.team == pyro.team || victim.health == 0 {
+                    continue;
+                }
+
+                if !PendingPush::in_reach(pyro.origin, pyro.eye.y, victim.origin) {
+                    continue;
+                }
+
+                pushes.push(PendingPush {
+                    pyro: pyro.user_id,
+                    weapon,
+                    origin: pyro.origin,
+                    victim: summary.entity_id,
+                    velocity: victim.velocity,
+                    tick: self.tick,
+                });
+            }
+        }
+
+        let mut pushed = vec![];
+        for push in std::mem::take(&mut self.pending_pushes) {
+            let Some(victim) = self.get_player(&push.victim) else {
+                continue;
+            };
+            if push.pushed(victim.origin, victim.velocity) {
+                pushed.push(push);
+            } else if push.pending(self.tick) {
+                self.pending_pushes.push(push);
+            }
+        }
+
+        for push in pushed {
+            // Only credit the push once, even if several airblasts were waiting on this player.
+            self.pending_pushes.retain(|p| p.victim != push.victim);
+
+            let Some(summary) = self
+                .user_id_to_steam_id
+                .get(&push.pyro)
+                .and_then(|sid| self.player_summaries.get_mut(sid))
+            else {
+                error!(
+                    "Could not find player summary for pyro {} that pushed",
+                    push.pyro
+                );
+                continue;
+            };
+            summary.handle_push(push.weapon);
+            let pyro = summary.steamid.clone();
+
+            let Some(victim) = self
+                .user_entities
+                .get(&push.victim)
+                .and_then(|uid| self.user_id_to_steam_id.get(uid))
+                .and_then(|sid| self.player_summaries.get_mut(sid))
+            else {
+                error!(
+                    "Could not find player summary for entity {} that was pushed",
+                    push.victim
+                );
+                continue;
+            };
+            victim
+                .recent_attackers
+                .record(self.tick, pyro, push.weapon, InteractionKind::Airblast);
+        }
+
+        self.pending_pushes.extend(pushes);
+    }
+
+    pub fn handle_player_extinguished(&mut self, e: &PlayerExtinguishedEvent) {
+        trace!("Player extinguished {:?}", e);
+
+        // Players putting themselves out (ie with a jar or medkit) don't count.
+        if e.healer == e.victim {
+            return;
+        }
+
+        let healer_eid = EntityId::from(e.healer as u32);
+        let Some(healer) = self.get_player(&healer_eid) else {
+            error!("Could not find player entity {healer_eid} that extinguished {e:?}");
+            return;
+        };
+        let Some(item) = self.schema.items.get(&(e.item_definition_index as u32)) else {
+            error!("Unknown item used to extinguish {e:?}");
+            return;
+        };
+        let weapon = weapon::weapon_name(item, healer.class);
+
+        let Some(summary) = self.get_player_summary_mut(&healer_eid) else {
+            error!("Could not find player summary for entity {healer_eid} that extinguished");
+            return;
+        };
+        summary.handle_extinguish(weapon);
+    }
+
+    // Height of the victim above the ground if this hurt counts as an airshot: a projectile or
+    // sniper headshot hitting a player that has been well off the ground for a while.
+    fn airshot_height(
+        &self,
+        victim_steamid: &str,
+        source: &HurtSource,
+        damage_type: DamageType,
+        attacker_class: Class,
+    ) -> Option<f32> {
+        let qualifies = match source {
+            HurtSource::Explosion(e) | HurtSource::NonBlastProjectile(e) => !e.projectile.is_sentry,
+            _ => {
+                attacker_class == Class::Sniper
+                    && matches!(
+                        damage_type,
+                        DamageType::Headshot | DamageType::HeadshotDecapitation
+                    )
+            }
+        };
+        if !qualifies {
+            return None;
+        }
+
+        self.player_summaries
+            .get(victim_steamid)?
+            .airshot_height(self.tick, &self.options.airshots)
+    }
+
+    // Weapon that applied the afterburn or bleed behind this hurt, if we saw it land.
+    fn dot_source(&self, hurt: &Hurt) -> Option<&'static str> {
+        let victim = self
+            .user_id_to_steam_id
+            .get(&hurt.victim)
+            .and_then(|sid| self.player_summaries.get(sid))?;
+        match hurt.kind {
+            DamageKind::Afterburn => victim.afterburn_sources.get(&hurt.attacker).copied(),
+            DamageKind::Bleed => victim.bleed_sources.get(&hurt.attacker).copied(),
+            DamageKind::Direct => None,
+        }
+    }
+
     fn handle_packet_entity(&mut self, packet: &PacketEntity, parser_state: &ParserState) {
         let Some(class) = parser_state
             .server_classes
@@ -627,6 +1465,7 @@ impl<'a> MatchAnalyzer<'a> {
                     "CObjectDispenser" => {
                         Box::new(entity::Dispenser::new(packet, parser_state, &mut ma))
                     }
+                    "CObjectSapper" => Box::new(entity::Sapper::new(packet, parser_state, &mut ma)),
                     "CTFPlayer" => Box::new(entity::Player::new(packet, parser_state, &mut ma)),
                     "CTFWearableDemoShield" => {
                         Box::new(entity::Shield::new(packet, parser_state, &mut ma))
@@ -637,6 +1476,8 @@ impl<'a> MatchAnalyzer<'a> {
                     _ if is_weapon => Box::new(entity::Weapon::new(packet, parser_state, &mut ma)),
                     _ => Box::new(entity::Unknown::new(packet, parser_state, &mut ma)),
                 };
+                self.handle_building_placed(packet.entity_index, e.as_ref());
+                self.handle_sapper_placed(packet.entity_index, e.as_ref());
                 self.entities[eid] = Some(e);
             }
             UpdateType::Preserve => {
@@ -674,6 +1515,9 @@ impl<'a> MatchAnalyzer<'a> {
             }
             UpdateType::Delete | UpdateType::Leave => {
                 self.deleted_entities.insert(packet.entity_index);
+                if let Some(track) = self.projectile_tracks.get_mut(&packet.entity_index) {
+                    track.detonate(self.tick);
+                }
 
                 if !packet.props.is_empty() {
                     error!(
@@ -715,6 +1559,21 @@ impl<'a> MatchAnalyzer<'a> {
                     e.leave(&mut ma);
                 }
 
+                if let Some(building) = self.buildings.handle_removed(
+                    packet.entity_index,
+                    self.tick,
+                    self.interval_per_tick,
+                ) {
+                    self.record_building(building);
+                }
+                if let Some(sapper) = self.sappers.handle_removed(
+                    packet.entity_index,
+                    self.tick,
+                    self.interval_per_tick,
+                ) {
+                    self.current_round.sappers.push(sapper);
+                }
+
                 let k = std::mem::take(&mut self.colliders[eid]);
                 if let Some(k) = k {
                     self.collider_set.remove(
@@ -732,10 +1591,25 @@ impl<'a> MatchAnalyzer<'a> {
             }
         }
 
+        if is_projectile {
+            self.track_projectile(packet.entity_index, packet.update_type == UpdateType::Enter);
+        }
+
         if let Some(e) = &self.entities[eid] {
             if let Some(h) = e.handle() {
                 self.entity_handles.insert(h, EntityId::from(eid as u32));
             }
+            if packet.update_type == UpdateType::Preserve
+                && let Some(state) = BuildingState::of(e.as_ref())
+            {
+                self.buildings.handle_update(
+                    packet.entity_index,
+                    self.tick,
+                    &state,
+                    self.interval_per_tick,
+                    self.regions.as_ref(),
+                );
+            }
 
             if let (Some(shape), Some(origin)) = (e.shape(), e.origin()) {
                 if let Some(collider) = self.colliders[eid] {
@@ -754,7 +1628,7 @@ impl<'a> MatchAnalyzer<'a> {
                     // Due to https://github.com/dimforge/parry/issues/51 we use ptr_eq and
                     // rely on shapes being statics; revisit this for performance if an
                     // entity ever dynamically computes its shape on every tick.
-                    if Arc::ptr_eq(&c.shared_shape().0, &shape.0) {
+                    if !Arc::ptr_eq(&c.shared_shape().0, &shape.0) {
                         c.set_shape(shape);
                     }
                     if c.position().translation != origin.into() {
@@ -762,7 +1636,10 @@ impl<'a> MatchAnalyzer<'a> {
                     }
                     self.mutated_colliders.push(collider);
                 } else {
-                    let mut c = ColliderBuilder::new(shape).position(origin.into()).build();
+                    let mut c = ColliderBuilder::new(shape)
+                        .position(origin.into())
+                        .collision_groups(InteractionGroups::new(ENTITY_GROUP, Group::ALL))
+                        .build();
                     c.user_data = eid as u128;
                     let k = self.collider_set.insert(c);
                     self.mutated_colliders.push(k);
@@ -772,6 +1649,98 @@ impl<'a> MatchAnalyzer<'a> {
         }
     }
 
+    fn track_projectile(&mut self, eid: EntityId, entered: bool) {
+        if entered && let Some(stale) = self.projectile_tracks.remove(&eid) {
+            self.finish_projectile(stale);
+        }
+
+        let Some(p) = self.entities[usize::from(eid)]
+            .as_ref()
+            .and_then(|e| e.projectile())
+        else {
+            return;
+        };
+
+        if !entered {
+            if let Some(track) = self.projectile_tracks.get_mut(&eid) {
+                track.sample(p, self.tick);
+            }
+            return;
+        }
+
+        if p.is_sentry {
+            return;
+        }
+        let Some(owner) = self
+            .entity_handles
+            .get(&p.original_owner)
+            .and_then(|eid| self.get_player(eid))
+        else {
+            return;
+        };
+        let Some(steamid) = self.user_id_to_steam_id.get(&owner.user_id).cloned() else {
+            error!("No steamid for owner of projectile {eid}");
+            return;
+        };
+        let Some(item) = p
+            .launcher_schema_id
+            .and_then(|id| self.schema.items.get(&id))
+        else {
+            return;
+        };
+
+        let track = ProjectileTrack::new(
+            steamid,
+            weapon::weapon_name(item, owner.class),
+            p,
+            self.tick,
+        );
+        self.projectile_tracks.insert(eid, track);
+    }
+
+    // Called once a projectile is gone and every hurt it caused has been seen.
+    fn finish_projectile(&mut self, mut track: ProjectileTrack) {
+        track.finish();
+
+        // Accuracy counts each projectile that hit someone once, no matter how many players it
+        // damaged. Reflected projectiles are a miss for whoever fired them.
+        if !track.reflected && !track.hits.is_empty() {
+            if let Some(owner) = self.player_summaries.get_mut(&track.owner) {
+                owner.handle_projectile_hit(&track);
+                // A projectile that hit several players counts once, at the range of the nearest.
+                if let Some(range) = track
+                    .nearest_hit()
+                    .and_then(|d| self.options.distance.range(d))
+                {
+                    owner.handle_ranged_hit(&track.weapon, range);
+                }
+            } else {
+                error!("Unknown owner {} of finished projectile", track.owner);
+            }
+
+            let class = |steamid: &str| self.player_summaries.get(steamid).map(|p| p.class);
+            if self.round_state != RoundState::TeamWin
+                && let Some(owner_class) = class(&track.owner)
+            {
+                for victim in track.victim_ids() {
+                    let Some(victim_class) = class(victim) else {
+                        continue;
+                    };
+                    self.current_round.handle_matchup_hit(
+                        &track.owner,
+                        victim,
+                        owner_class,
+                        victim_class,
+                    );
+                }
+            }
+        }
+
+        if self.options.projectiles {
+            self.current_round.projectiles.push(track);
+        }
+    }
+
     pub fn handle_player_resource(&mut self, entity: &PacketEntity, _parser_state: &ParserState) {
         for prop in &entity.props {
             let Some((table_name, prop_name)) = prop.identifier.names() else {
@@ -884,7 +1853,15 @@ impl<'a> MatchAnalyzer<'a> {
                     trace!("Waiting for players: {}", self.waiting_for_players);
                 }
                 (ROUND_STATE, SendPropValue::Integer(x)) => match RoundState::try_from(*x as u16) {
-                    Ok(x) => self.round_state = x,
+                    Ok(x) => {
+                        // Time between rounds isn't spent anywhere.
+                        if x != self.round_state
+                            && matches!(x, RoundState::PreRound | RoundState::Running)
+                        {
+                            self.region_tick = Some(self.tick);
+                        }
+                        self.round_state = x;
+                    }
                     Err(e) => error!("Could not parse RoundState: {e}"),
                 },
                 (id, value) => {
@@ -970,6 +1947,8 @@ impl<'a> MatchAnalyzer<'a> {
                     if self.round_state != RoundState::TeamWin {
                         suicider.suicides += 1;
                     }
+                    suicider.recent_attackers = Default::default();
+                    self.handle_death_recap(&steamid, Some(steamid.clone()));
                 } else {
                     error!("Unknown suicider steamid for user_id: {}", attacker_user_id);
                 }
@@ -991,12 +1970,14 @@ impl<'a> MatchAnalyzer<'a> {
             return;
         };
 
-        let victim_summary_for_eid_lookup = self.player_summaries.get(&victim_steamid);
+        let victim_summary_for_eid_lookup = self.player_summaries.get_mut(&victim_steamid);
         let Some(victim_summary_for_eid_lookup) = victim_summary_for_eid_lookup else {
             error!("Unknown victim summary for steamid: {}", victim_steamid);
             return;
         };
         let victim_eid = victim_summary_for_eid_lookup.entity_id;
+        // Whatever happened before this death can't be credited for a later one.
+        let recent_attackers = std::mem::take(&mut victim_summary_for_eid_lookup.recent_attackers);
 
         let Some(victim_e) = self.get_player(&victim_eid) else {
             error!("No victim entity for entity_id: {}", victim_eid);
@@ -1022,12 +2003,48 @@ impl<'a> MatchAnalyzer<'a> {
         }
 
         victim.handle_death(self.round_state, flags);
+        let victim_class = victim.class;
 
-        let airshot = victim.in_air() && (self.tick - victim.started_flying > 16);
+        if !feigned {
+            let killer = (death.attacker != 0)
+                .then(|| self.user_id_to_steam_id.get(&attacker_user_id).cloned())
+                .flatten();
+            self.handle_death_recap(&victim_steamid, killer);
+        }
+
+        let airshot = self
+            .hurts
+            .iter()
+            .any(|h| h.victim == victim_user_id && h.attacker == attacker_user_id && h.airshot);
+        if airshot
+            && let Some(a) = self
+                .current_round
+                .airshots
+                .iter_mut()
+                .rev()
+                .find(|a| a.tick == self.tick && a.victim == victim_steamid)
+        {
+            a.kill = true;
+        }
 
         let attacker_is_world = death.attacker == 0;
         let attacker_is_world_wep = death.weapon_def_index == 0xffff;
         if attacker_is_world || attacker_is_world_wep {
+            let environmental = attacker_is_world
+                || damage_type == DamageType::TriggerHurt
+                || damage_bits.contains(Damage::Fall)
+                || damage_bits.contains(Damage::Crush)
+                || damage_bits.contains(Damage::Vehicle)
+                || damage_bits.contains(Damage::Drown);
+            if environmental && !feigned {
+                self.handle_environment_death(
+                    death,
+                    damage_type,
+                    damage_bits,
+                    &victim_steamid,
+                    &recent_attackers,
+                );
+            }
             return;
         }
 
@@ -1076,6 +2093,23 @@ impl<'a> MatchAnalyzer<'a> {
             let my_name =
                 self.weapon_name_from_damage(damage_type, damage_bits, victim_e, attacker_e, None);
 
+            let kill_distance = EuclideanSpace::distance(&attacker_e.origin, &victim_e.origin);
+            let (attacker_team, victim_team) = (attacker_e.team, victim_e.team);
+            let (attacker_origin, victim_origin) = (attacker_e.origin, victim_e.origin);
+            let attacker_region = self.region_at(&attacker_origin);
+            let victim_region = self.region_at(&victim_origin);
+            let unseen_window = self.ticks(self.options.visibility.unseen_window);
+            let unseen = self.world_collider.is_some()
+                && self.options.visibility.interval > 0
+                && !weapon::is_sentry(my_name)
+                && attacker_summary_for_eid_lookup.unseen(
+                    &victim_steamid,
+                    self.tick,
+                    unseen_window,
+                );
+            let blast_jumping = attacker_e.condition.contains(PlayerCondition::BlastJumping);
+            let market_garden = is_market_garden(blast_jumping, death.weapon_id);
+
             if *my_name != format!("{}", death.weapon_log_class_name) {
                 error!(
                     "log names disagree log:{} vs us:{}",
@@ -1094,14 +2128,132 @@ impl<'a> MatchAnalyzer<'a> {
                 death.weapon_log_class_name,
             );
 
+            self.current_round.handle_matchup_kill(
+                &attacker_steamid,
+                &victim_steamid,
+                attacker_summary_for_eid_lookup.class,
+                victim_class,
+            );
+
+            if self.options.map_events {
+                self.current_round.map_events.push(MapEvent {
+                    tick: self.tick,
+                    kind: MapEventKind::Kill,
+                    team: attacker_team,
+                    player: attacker_steamid.clone(),
+                    origin: attacker_origin,
+                    region: attacker_region.clone(),
+                    detail: Some(my_name.to_string()),
+                });
+                self.current_round.map_events.push(MapEvent {
+                    tick: self.tick,
+                    kind: MapEventKind::Death,
+                    team: victim_team,
+                    player: victim_steamid.clone(),
+                    origin: victim_origin,
+                    region: victim_region.clone(),
+                    detail: Some(my_name.to_string()),
+                });
+            }
+            if let Some(engagements) = &mut self.engagements {
+                engagements.push(Engagement {
+                    tick: self.tick,
+                    kind: EngagementKind::Kill,
+                    weapon: my_name.to_string(),
+                    damage: None,
+                    attacker: attacker_steamid.clone(),
+                    attacker_class: attacker_summary_for_eid_lookup.class,
+                    attacker_team,
+                    attacker_origin,
+                    attacker_region: attacker_region.clone(),
+                    victim: victim_steamid.clone(),
+                    victim_class,
+                    victim_team,
+                    victim_origin,
+                    victim_region: victim_region.clone(),
+                });
+            }
+
+            let lull = self.ticks(self.options.fights.lull);
+            let trade_window = self.ticks(self.options.fights.trade_window);
+            let context = self.fight.handle_kill(
+                self.tick,
+                lull,
+                trade_window,
+                &Combatant {
+                    steamid: &attacker_steamid,
+                    team: attacker_team,
+                    origin: attacker_origin,
+                },
+                &Combatant {
+                    steamid: &victim_steamid,
+                    team: victim_team,
+                    origin: victim_origin,
+                },
+            );
+
+            let sentry_fire = weapon::is_sentry(my_name)
+                .then(|| weapon::sentry_fire(damage_type, death.weapon_id));
+            if let Some(fire) = sentry_fire {
+                let inflictor = EntityId::from(death.inflictor_ent_index);
+                if let Some(sentry) = self.sentry_fired(inflictor).and_then(|s| {
+                    self.buildings
+                        .sentry(&mut self.current_round.buildings, s, self.tick)
+                }) {
+                    sentry.handle_sentry_kill(fire);
+                }
+            }
+
             if let Some(attacker) = self.player_summaries.get_mut(&attacker_steamid) {
                 attacker.handle_kill(self.round_state, my_name, flags, damage_type, airshot);
+                if let Some(fire) = sentry_fire {
+                    attacker.handle_sentry_kill(fire);
+                }
+                if weapon::is_reflect(my_name) {
+                    attacker.handle_reflect_kill(my_name);
+                }
+                if blast_jumping {
+                    attacker.handle_blast_jump_kill(my_name, market_garden);
+                }
+                if !weapon::is_sentry(my_name)
+                    && let Some(range) = self.options.distance.range(kill_distance)
+                {
+                    attacker.handle_ranged_kill(my_name, range, kill_distance);
+                }
+                if let Some(region) = &attacker_region {
+                    attacker.handle_region_kill(my_name, region);
+                }
+                if unseen {
+                    attacker.handle_unseen_kill(my_name);
+                }
+                if context.first_pick {
+                    attacker.handle_first_pick(my_name);
+                }
+                if context.traded.is_some() {
+                    attacker.handle_trade(my_name);
+                }
             } else {
                 error!(
                     "Failed to get mutable attacker summary for steamid: {}",
                     attacker_steamid
                 );
             }
+
+            if let Some(region) = &victim_region
+                && let Some(victim) = self.player_summaries.get_mut(&victim_steamid)
+            {
+                victim.handle_region_death(region);
+            }
+            if context.first_pick
+                && let Some(victim) = self.player_summaries.get_mut(&victim_steamid)
+            {
+                victim.handle_first_death();
+            }
+            if let Some(traded) = &context.traded
+                && let Some(teammate) = self.player_summaries.get_mut(traded)
+            {
+                teammate.handle_traded_death();
+            }
         }
 
         if death.assister == 0xffff {
@@ -1200,9 +2352,13 @@ impl<'a> MatchAnalyzer<'a> {
             && hurt.weapon_id == 0
             && hurt.custom == 0
             && hurt.bonus_effect == 0;
-        if hurt.attacker == hurt.user_id || fall_damage {
-            // No need to track self damage or fall damage for now
-            // TODO: maybe for rocket jumping or uber building?
+        if hurt.attacker == hurt.user_id {
+            self.handle_self_damage(hurt);
+            return;
+        }
+        if hurt.attacker == 0 {
+            // Huge fall damage amounts >=500 are typically kill zones like falling out of a map.
+            self.handle_world_damage(hurt, fall_damage && hurt.damage_amount < 500);
             return;
         }
 
@@ -1241,10 +2397,24 @@ impl<'a> MatchAnalyzer<'a> {
             return;
         };
         let victim_origin = victim_summary_for_lookup.origin;
+        let victim_crouched = self
+            .get_player(&victim_summary_for_lookup.entity_id)
+            .is_some_and(|p| p.ducked);
+
+        let afterburn = is_afterburn(
+            victim_summary_for_lookup,
+            hurt,
+            damage_type,
+            &weapon_type,
+            self.tick,
+            self.ticks(AFTERBURN_PERIOD),
+        );
 
         let mut source = HurtSource::Unknown;
 
-        if attacker_class == Class::Engineer && damage_type == DamageType::Normal {
+        if attacker_class == Class::Engineer
+            && (damage_type == DamageType::Normal || damage_type == DamageType::PlayerSentry)
+        {
             let remove_idx = if let Some((idx, s)) = self
                 .sentry_shots
                 .iter()
@@ -1284,7 +2454,7 @@ impl<'a> MatchAnalyzer<'a> {
                 trace!("look at explosions {:?}", exps);
                 exps.sort_by(|a, b| a.1.total_cmp(&b.1));
                 let playerbox =
-                    Cuboid::new(Vector::new(49.0, 49.0, 83.0)).aabb(&victim_origin.into());
+                    entity::player_hull(victim_crouched).compute_aabb(&victim_origin.into());
 
                 let hit_exps = exps
                     .into_iter()
@@ -1325,27 +2495,6 @@ impl<'a> MatchAnalyzer<'a> {
             }
         }
 
-        if hurt.attacker == 0 {
-            if self.round_state == RoundState::TeamWin && hurt.damage_amount == 5000 {
-                // Explosion at the end of some maps
-                return;
-            }
-
-            // Huge fall damage amounts >=500 are typically kill zones like falling out of a map.
-            if hurt.damage_amount <= 500
-                && (damage_type != DamageType::Normal
-                    || effect != DamageEffect::Crit
-                    || weapon_type != WeaponId::None)
-            {
-                error!(
-                    "Weird fall damage {} {damage_type:?} {effect:?} {weapon_type:?} {:?}",
-                    hurt.damage_amount, self.round_state
-                );
-            }
-
-            return;
-        }
-
         let Some(attacker_summary_for_entity_lookup) = self.player_summaries.get(&attacker_steamid)
         else {
             error!("Unknown attacker summary for steamid {attacker_steamid} in player hurt event");
@@ -1368,6 +2517,36 @@ impl<'a> MatchAnalyzer<'a> {
             error!("Unknown entity for victim steamid {}", victim_steamid);
             return;
         };
+        let victim_class = victim_summary_for_entity_lookup.class;
+        let victim_team = victim_e.team;
+
+        let kind = if damage_type == DamageType::Bleeding {
+            DamageKind::Bleed
+        } else if afterburn {
+            DamageKind::Afterburn
+        } else {
+            DamageKind::Direct
+        };
+        let distance = EuclideanSpace::distance(&attacker_e.origin, &victim_e.origin);
+        let attacker_origin = attacker_e.origin;
+        let victim_condition = victim_e.condition;
+        let causes_bleed = self
+            .get_weapon(&attacker_wep)
+            .and_then(|w| self.schema.items.get(&w.schema_id))
+            .is_some_and(weapon::causes_bleed);
+
+        let reflected = match &source {
+            HurtSource::Explosion(e) | HurtSource::NonBlastProjectile(e) => {
+                e.projectile.is_reflected && e.projectile.owner == attacker_handle
+            }
+            _ => false,
+        };
+
+        let airshot_height = if kind == DamageKind::Direct {
+            self.airshot_height(&victim_steamid, &source, damage_type, attacker_class)
+        } else {
+            None
+        };
 
         let hurt_event = Hurt {
             victim: victim_user_id,
@@ -1375,6 +2554,8 @@ impl<'a> MatchAnalyzer<'a> {
             wep: attacker_wep,
             origin: victim_origin,
             source,
+            kind,
+            airshot: airshot_height.is_some(),
         };
         let weapon_name = self.weapon_name_from_damage(
             damage_type,
@@ -1384,6 +2565,20 @@ impl<'a> MatchAnalyzer<'a> {
             Some(&hurt_event),
         );
 
+        let mut projectile_hit = false;
+        if let HurtSource::Explosion(e) | HurtSource::NonBlastProjectile(e) = &hurt_event.source
+            && let Some(track) = self.projectile_tracks.get_mut(&e.projectile.id)
+        {
+            track.hits.push(ProjectileHit {
+                victim: victim_steamid.clone(),
+                damage: hurt.damage_amount as u32,
+                direct: matches!(hurt_event.source, HurtSource::NonBlastProjectile(_))
+                    || trajectory::is_direct_hit(&e.projectile, &victim_origin, victim_crouched),
+                distance,
+            });
+            projectile_hit = true;
+        }
+
         let Some(victim) = self.player_summaries.get_mut(&victim_steamid) else {
             error!(
                 "Unknown victim summary (mut) for steamid {victim_steamid} in player hurt event"
@@ -1391,6 +2586,43 @@ impl<'a> MatchAnalyzer<'a> {
             return;
         };
         victim.handle_damage_taken(weapon_name, hurt, damage_type);
+        if kind == DamageKind::Afterburn {
+            victim.afterburn_tick = self.tick;
+        }
+        victim.damage_log.record_damage(DamageInstance {
+            tick: self.tick,
+            attacker: Some(attacker_steamid.clone()),
+            weapon: Some(weapon_name.to_string()),
+            amount: hurt.damage_amount as u32,
+            kind,
+            crit: CritType::from_hurt(hurt),
+            distance,
+            health: hurt.health as u32,
+        });
+        victim.recent_attackers.record(
+            self.tick,
+            attacker_steamid.clone(),
+            weapon_name,
+            match hurt_event.source {
+                HurtSource::Explosion(_) => InteractionKind::Knockback,
+                _ => InteractionKind::Damage,
+            },
+        );
+
+        if kind == DamageKind::Direct {
+            if victim_condition.contains(PlayerCondition::Burning)
+                && (victim.ignited == self.tick || damage_type.is_fire())
+            {
+                victim
+                    .afterburn_sources
+                    .insert(attacker_user_id, weapon_name);
+            }
+            if victim_condition.contains(PlayerCondition::Bleeding)
+                && (victim.started_bleeding == self.tick || causes_bleed)
+            {
+                victim.bleed_sources.insert(attacker_user_id, weapon_name);
+            }
+        }
 
         if let Some(wep) = self.get_weapon(&attacker_wep) {
             let Some(wi) = self.schema.items.get(&wep.schema_id) else {
@@ -1408,6 +2640,7 @@ impl<'a> MatchAnalyzer<'a> {
                 hurt.weapon_id
             );
         }
+        let lull = self.ticks(self.options.fights.lull);
         let Some(attacker) = self.player_summaries.get_mut(&attacker_steamid) else {
             error!(
                 "Unknown attacker summary (mut) for steamid {attacker_steamid} in player hurt event"
@@ -1415,14 +2648,126 @@ impl<'a> MatchAnalyzer<'a> {
             return;
         };
 
-        attacker.handle_damage_dealt(weapon_name, hurt, damage_type);
-
-        // TODO: Handle initial flamethrower hits; ignore
-        if damage_type != DamageType::Burning
-            && damage_type != DamageType::BurningFlare
+        attacker.handle_damage_dealt(weapon_name, hurt, damage_type, kind);
+        if reflected {
+            attacker.handle_reflect_damage(weapon_name, hurt);
+        }
+        if weapon::is_sentry(weapon_name) {
+            let fire = weapon::sentry_fire(damage_type, hurt.weapon_id);
+            attacker.handle_sentry_damage(hurt.damage_amount as u32, fire);
+
+            let sentry = match &hurt_event.source {
+                HurtSource::SentryShot(s) => Some(s.id),
+                HurtSource::Explosion(e) | HurtSource::NonBlastProjectile(e) => e.projectile.sentry,
+                HurtSource::Unknown => None,
+            };
+            if let Some(sentry) = sentry.and_then(|s| {
+                self.buildings
+                    .sentry(&mut self.current_round.buildings, s, self.tick)
+            }) {
+                sentry.handle_sentry_damage(hurt.damage_amount as u32, fire);
+            }
+        }
+        // Damage over time and sentry fire are nowhere near the attacker, so their distance
+        // means nothing.
+        if kind == DamageKind::Direct
             && !weapon::is_sentry(weapon_name)
+            && let Some(range) = self.options.distance.range(distance)
         {
-            attacker.handle_shot_hit(weapon_name);
+            attacker.handle_ranged_damage(weapon_name, range, hurt);
+        }
+        if self.round_state != RoundState::TeamWin {
+            self.fight.handle_damage(
+                self.tick,
+                lull,
+                &Combatant {
+                    steamid: &attacker_steamid,
+                    team: attacker_team,
+                    origin: attacker_origin,
+                },
+                &Combatant {
+                    steamid: &victim_steamid,
+                    team: victim_team,
+                    origin: victim_origin,
+                },
+                hurt.damage_amount as u32,
+            );
+            if let Some(engagements) = &mut self.engagements {
+                let regions = self.regions.as_ref();
+                engagements.push(Engagement {
+                    tick: self.tick,
+                    kind: EngagementKind::Damage,
+                    weapon: weapon_name.to_string(),
+                    damage: Some(hurt.damage_amount as u32),
+                    attacker: attacker_steamid.clone(),
+                    attacker_class,
+                    attacker_team,
+                    attacker_origin,
+                    attacker_region: regions
+                        .and_then(|r| r.region_at(&attacker_origin))
+                        .map(String::from),
+                    victim: victim_steamid.clone(),
+                    victim_class,
+                    victim_team,
+                    victim_origin,
+                    victim_region: regions
+                        .and_then(|r| r.region_at(&victim_origin))
+                        .map(String::from),
+                });
+            }
+            self.current_round.handle_matchup_damage(
+                &attacker_steamid,
+                &victim_steamid,
+                attacker_class,
+                victim_class,
+                hurt.damage_amount as u32,
+            );
+        }
+
+        // Damage over time never counts as a hit, and projectile hits are counted when the
+        // projectile is finished with.
+        if kind == DamageKind::Direct && !weapon::is_sentry(weapon_name) && !projectile_hit {
+            let range = self.options.distance.range(distance);
+            let hit = if damage_type == DamageType::Burning {
+                // Flames are counted as one particle hit per tick, however many players they
+                // touched, to match how flamethrower shots are counted.
+                if !self.flame_hits.iter().any(|(a, _)| *a == attacker_user_id) {
+                    attacker.handle_shot_hit(weapon_name);
+                    if let Some(range) = range {
+                        attacker.handle_ranged_hit(weapon_name, range);
+                    }
+                }
+                self.flame_hits.insert((attacker_user_id, victim_user_id))
+            } else if damage_type != DamageType::BurningFlare {
+                attacker.handle_shot_hit(weapon_name);
+                if let Some(range) = range {
+                    attacker.handle_ranged_hit(weapon_name, range);
+                }
+                true
+            } else {
+                false
+            };
+            if hit && self.round_state != RoundState::TeamWin {
+                self.current_round.handle_matchup_hit(
+                    &attacker_steamid,
+                    &victim_steamid,
+                    attacker_class,
+                    victim_class,
+                );
+            }
+        }
+
+        if let Some(height) = airshot_height {
+            attacker.handle_airshot_hit(weapon_name);
+            self.current_round.airshots.push(Airshot {
+                tick: self.tick,
+                attacker: attacker_steamid.clone(),
+                victim: victim_steamid.clone(),
+                weapon: weapon_name.into(),
+                height,
+                distance,
+                kill: false, // set when the death comes in
+            });
         }
 
         if hurt.health == 0 {
@@ -1430,6 +2775,191 @@ impl<'a> MatchAnalyzer<'a> {
         }
     }
 
+    // Skirmishes with no kills and only a little chip damage aren't worth reporting as fights.
+    fn finish_fights(&mut self) {
+        let min_damage = self.options.fights.min_damage;
+        self.current_round.fights = self
+            .fight
+            .take_fights()
+            .into_iter()
+            .filter(|f| f.kills() > 0 || f.damage() >= min_damage)
+            .collect();
+        for i in 0..self.current_round.fights.len() {
+            let region = self.region_at(&self.current_round.fights[i].centroid);
+            self.current_round.fights[i].region = region;
+        }
+    }
+
+    fn record_building(&mut self, building: BuildingRecord) {
+        if building.shielded_time > 0.0
+            && let Some(owner) = self.player_summaries.get_mut(&building.owner)
+        {
+            owner.handle_wrangler_shield(building.shielded_time);
+        }
+        self.current_round.buildings.push(building);
+    }
+
+    // Buildings and sappers still up at the end of a round.
+    fn finish_buildings(&mut self) {
+        let buildings = self
+            .buildings
+            .take_standing(self.tick, self.interval_per_tick);
+        for building in buildings {
+            self.record_building(building);
+        }
+        let sappers = self
+            .sappers
+            .take_standing(self.tick, self.interval_per_tick);
+        self.current_round.sappers.extend(sappers);
+    }
+
+    fn handle_death_recap(&mut self, victim_steamid: &str, killer: Option<String>) {
+        let window = self.ticks(self.options.death_recaps.window);
+        let region = self
+            .player_summaries
+            .get(victim_steamid)
+            .and_then(|p| self.get_player(&p.entity_id))
+            .and_then(|p| self.region_at(&p.origin));
+        let Some(victim) = self.player_summaries.get_mut(victim_steamid) else {
+            error!("Unknown victim summary for steamid {victim_steamid} in death recap");
+            return;
+        };
+        victim.damage_log.prune(self.tick, window);
+        let recap = victim
+            .damage_log
+            .recap(self.tick, victim_steamid.to_string(), killer, region);
+        self.current_round.deaths.push(recap);
+    }
+
+    fn handle_self_damage(&mut self, hurt: &PlayerHurtEvent) {
+        let user_id = UserId::from(hurt.user_id);
+        let Some(steamid) = self.user_id_to_steam_id.get(&user_id).cloned() else {
+            error!("Unknown steamid mapping for user_id {user_id} in self damage");
+            return;
+        };
+        let Some(entity_id) = self.player_summaries.get(&steamid).map(|p| p.entity_id) else {
+            error!("Unknown summary for steamid {steamid} in self damage");
+            return;
+        };
+        // The jump's own damage lands on the same tick the condition is applied.
+        let blast_jumping = self
+            .get_player(&entity_id)
+            .is_some_and(|p| p.condition.contains(PlayerCondition::BlastJumping));
+
+        let Some(player) = self.player_summaries.get_mut(&steamid) else {
+            return;
+        };
+        player.handle_self_damage(hurt);
+        if blast_jumping {
+            player.handle_blast_jump_damage(hurt);
+        }
+        player.damage_log.record_damage(DamageInstance {
+            tick: self.tick,
+            attacker: Some(steamid.clone()),
+            weapon: None,
+            amount: hurt.damage_amount as u32,
+            kind: DamageKind::Direct,
+            crit: CritType::from_hurt(hurt),
+            distance: 0.0,
+            health: hurt.health as u32,
+        });
+    }
+
+    fn handle_world_damage(&mut self, hurt: &PlayerHurtEvent, fall: bool) {
+        if self.round_state == RoundState::TeamWin && hurt.damage_amount == 5000 {
+            // Explosion at the end of some maps
+            return;
+        }
+
+        let user_id = UserId::from(hurt.user_id);
+        let Some(steamid) = self.user_id_to_steam_id.get(&user_id).cloned() else {
+            error!("Unknown steamid mapping for user_id {user_id} in world damage");
+            return;
+        };
+        let Some(player) = self.player_summaries.get_mut(&steamid) else {
+            error!("Unknown summary for steamid {steamid} in world damage");
+            return;
+        };
+
+        if fall {
+            player.handle_fall_damage(hurt);
+        } else {
+            player.handle_environment_damage(hurt);
+        }
+        player.damage_log.record_damage(DamageInstance {
+            tick: self.tick,
+            attacker: None,
+            weapon: Some(if fall { "fall" } else { "world" }.to_string()),
+            amount: hurt.damage_amount as u32,
+            kind: DamageKind::Direct,
+            crit: CritType::None,
+            distance: 0.0,
+            health: hurt.health as u32,
+        });
+    }
+
+    fn handle_environment_death(
+        &mut self,
+        death: &PlayerDeathEvent,
+        damage_type: DamageType,
+        damage_bits: EnumSet<Damage>,
+        victim_steamid: &str,
+        recent_attackers: &InteractionHistory,
+    ) {
+        let cause = if damage_type == DamageType::TriggerHurt {
+            EnvironmentCause::TriggerHurt
+        } else if damage_bits.contains(Damage::Fall) {
+            EnvironmentCause::Fall
+        } else if damage_bits.contains(Damage::Crush) || damage_bits.contains(Damage::Vehicle) {
+            EnvironmentCause::Crush
+        } else if damage_bits.contains(Damage::Drown) {
+            EnvironmentCause::Drown
+        } else {
+            EnvironmentCause::Other
+        };
+
+        let Some(victim) = self.player_summaries.get_mut(victim_steamid) else {
+            error!("Unknown victim summary for steamid {victim_steamid} in environment death");
+            return;
+        };
+        victim.handle_environment_death();
+
+        // Whoever last hurt or pushed the victim gets the kill, as long as it was recent enough.
+        let window = self.ticks(self.options.environment.kill_window);
+        let last = recent_attackers.last_within(self.tick, window);
+
+        // The game sometimes credits the last attacker itself, which is more reliable when it
+        // disagrees with what we saw.
+        let attacker_user_id = UserId::from(death.attacker as u32);
+        let credited = if death.attacker != 0 && death.attacker != death.user_id {
+            self.user_id_to_steam_id.get(&attacker_user_id).cloned()
+        } else {
+            last.map(|i| i.attacker.clone())
+        };
+        let last = last.filter(|i| Some(&i.attacker) == credited.as_ref());
+
+        if self.round_state != RoundState::TeamWin
+            && let Some(credited) = &credited
+        {
+            if let Some(attacker) = self.player_summaries.get_mut(credited) {
+                attacker.handle_environmental_kill(last.map(|i| i.weapon));
+            } else {
+                error!("Unknown summary for steamid {credited} credited with environment kill");
+            }
+        }
+
+        self.current_round
+            .environment_deaths
+            .push(EnvironmentDeath {
+                tick: self.tick,
+                victim: victim_steamid.to_string(),
+                cause,
+                weapon: last.map(|i| i.weapon.to_string()),
+                via: last.map(|i| i.kind),
+                credited,
+            });
+    }
+
     pub fn handle_tick(&mut self, tick: &DemoTick, server_tick: Option<&NetTickMessage>) {
         if *tick != self.tick {
             self.on_tick();
@@ -1438,6 +2968,7 @@ impl<'a> MatchAnalyzer<'a> {
         self.hurts.drain(..);
         self.sentry_shots.drain(..);
         self.airblasts.drain();
+        self.flame_hits.drain();
         self.deleted_entities.drain();
 
         self.tick = *tick;
@@ -1460,18 +2991,42 @@ impl<'a> MatchAnalyzer<'a> {
     // processed. This is important when referring to entities that
     // may have been both created and referenced in the same packet.
     fn on_tick(&mut self) {
+        let recap_window = self.ticks(self.options.death_recaps.window);
+        for p in self.player_summaries.values_mut() {
+            p.damage_log.prune(self.tick, recap_window);
+        }
+        self.track_region_time();
+        self.sample_visibility();
+        self.sample_dispensers();
+
+        let mut flame_shots = vec![];
         for v in self.player_summaries.values() {
             let Some(e) = self.get_player(&v.entity_id) else {
                 continue;
             };
             if e.active_weapon_handle != 0 && e.active_weapon_handle != INVALID_HANDLE {
-                let Some(_) = self.get_weapon(&e.active_weapon_handle) else {
+                let Some(w) = self.get_weapon(&e.active_weapon_handle) else {
                     error!("could not find weapon handle {:?}", e.active_weapon_handle);
                     continue;
                 };
+
+                // Flamethrowers don't fire bullets or projectiles, so count every tick spent
+                // firing flames as a shot.
+                if w.is_firing_flames()
+                    && let Some(item) = self.schema.items.get(&w.schema_id)
+                {
+                    flame_shots.push((v.steamid.clone(), weapon::weapon_name(item, e.class)));
+                }
+            }
+        }
+        for (steamid, weapon) in flame_shots {
+            if let Some(p) = self.player_summaries.get_mut(&steamid) {
+                p.handle_fire_shot(weapon);
             }
         }
 
+        self.track_airblast_pushes();
+
         let t: Vec<_> = self.tick_events.drain(..).collect();
         for e in t {
             match e {
@@ -1501,6 +3056,12 @@ impl<'a> MatchAnalyzer<'a> {
                         );
                         continue;
                     };
+                    let (team, origin): (Option<Team>, Option<Vec3>) = self
+                        .player_summaries
+                        .get(&steamid)
+                        .and_then(|p| self.get_player(&p.entity_id))
+                        .map(|p| (p.team, p.origin))
+                        .unzip();
                     let Some(player) = self.player_summaries.get_mut(&steamid) else {
                         error!(
                             "Invalid owner steamid {steamid} for medigun {handle} when it was charged"
@@ -1508,13 +3069,284 @@ impl<'a> MatchAnalyzer<'a> {
                         continue;
                     };
                     player.handle_charged(item);
+
+                    if self.round_state != RoundState::TeamWin
+                        && let Some(team) = team
+                    {
+                        let lull = self.ticks(self.options.fights.lull);
+                        self.fight.handle_uber(self.tick, lull, &steamid, team);
+
+                        if self.options.map_events
+                            && let Some(origin) = origin
+                        {
+                            self.current_round.map_events.push(MapEvent {
+                                tick: self.tick,
+                                kind: MapEventKind::Uber,
+                                team,
+                                player: steamid,
+                                origin,
+                                region: self.region_at(&origin),
+                                detail: None,
+                            });
+                        }
+                    }
+                }
+                Event::Teleported(user_id) => {
+                    self.handle_teleported(user_id);
                 }
             }
         }
 
+        let mut finished: Vec<_> = self
+            .deleted_entities
+            .iter()
+            .filter_map(|eid| self.projectile_tracks.remove(eid))
+            .collect();
+        finished.sort_by_key(|t| t.spawn_tick);
+        for track in finished {
+            self.finish_projectile(track);
+        }
+
+        self.sample_positions();
+
         self.explosions.clear();
     }
 
+    fn handle_building_destroyed(
+        &mut self,
+        building: EntityId,
+        attacker: Option<String>,
+        weapon: &str,
+    ) {
+        if !self
+            .buildings
+            .handle_destroyed(building, self.tick, attacker.clone(), weapon)
+        {
+            building::handle_late_destruction(
+                &mut self.current_round.buildings,
+                building,
+                self.tick,
+                attacker,
+                weapon,
+            );
+        }
+
+        let mut spies = self.sappers.handle_target_destroyed(building);
+        spies.extend(building::handle_late_target_destruction(
+            &mut self.current_round.sappers,
+            building,
+            self.tick,
+        ));
+        for spy in spies {
+            if let Some(p) = self.player_summaries.get_mut(&spy) {
+                p.handle_sapped_building_destroyed();
+            }
+        }
+    }
+
+    fn handle_sapper_removed(&mut self, sapper: EntityId, remover: Option<String>, weapon: &str) {
+        let removed = match self
+            .sappers
+            .handle_sapper_destroyed(sapper, remover.clone(), weapon)
+        {
+            Some(removed) => removed,
+            None => building::handle_late_sapper_removal(
+                &mut self.current_round.sappers,
+                sapper,
+                self.tick,
+                remover.clone(),
+                weapon,
+            ),
+        };
+        if removed
+            && let Some(remover) = remover
+            && let Some(p) = self.player_summaries.get_mut(&remover)
+        {
+            p.handle_sapper_removed(weapon);
+        }
+    }
+
+    fn handle_sapper_placed(&mut self, id: EntityId, e: &dyn Entity) {
+        let Some(sapper) = e.sapper() else {
+            return;
+        };
+        let Some(spy) = self
+            .entity_handles
+            .get(&sapper.owner)
+            .and_then(|eid| self.get_player(eid))
+        else {
+            error!("Could not find player entity that placed sapper {id}");
+            return;
+        };
+        let team = spy.team;
+        let Some(spy_steamid) = self.user_id_to_steam_id.get(&spy.user_id).cloned() else {
+            error!("Could not find steamid for player that placed sapper {id}");
+            return;
+        };
+
+        let target_entity = handle_entity(sapper.built_on);
+        let target = self.entities[usize::from(target_entity)]
+            .as_ref()
+            .filter(|_| sapper.built_on != INVALID_HANDLE);
+        let (target, target_entity, target_owner) = if let Some(t) = target
+            && let Some(state) = BuildingState::of(t.as_ref())
+        {
+            let owner = t
+                .owner()
+                .and_then(|o| self.builder(o))
+                .map(|(steamid, _)| steamid);
+            (SapTarget::Building(state.kind), target_entity, owner)
+        } else if let Some(robot) = target.and_then(|t| t.player()) {
+            let owner = self.user_id_to_steam_id.get(&robot.user_id).cloned();
+            (SapTarget::Robot, target_entity, owner)
+        } else if let Some((eid, robot)) = self.sapped_robot_near(&sapper.origin) {
+            let owner = self.user_id_to_steam_id.get(&robot.user_id).cloned();
+            (SapTarget::Robot, eid, owner)
+        } else {
+            error!("Could not find what sapper {id} was placed on");
+            return;
+        };
+
+        self.sappers.handle_placed(SapperRecord::new(
+            id,
+            spy_steamid.clone(),
+            team,
+            target,
+            target_entity,
+            target_owner,
+            self.tick,
+        ));
+        if let Some(p) = self.player_summaries.get_mut(&spy_steamid) {
+            p.handle_sapper_placed();
+        }
+    }
+
+    // The closest MvM robot with a sapper on it.
+    fn sapped_robot_near(&self, origin: &Vec3) -> Option<(EntityId, &entity::Player)> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| Some((EntityId::from(i as u32), e.as_ref()?.player()?)))
+            .filter(|(_, p)| p.condition.contains(PlayerCondition::BotSapped))
+            .map(|(eid, p)| (eid, p, EuclideanSpace::distance(&p.origin, origin)))
+            .filter(|(_, _, distance)| *distance <= ROBOT_SAPPER_RANGE)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(eid, p, _)| (eid, p))
+    }
+
+    fn handle_building_placed(&mut self, id: EntityId, e: &dyn Entity) {
+        let kind = match e.class() {
+            EntityClass::Sentry => "sentry",
+            EntityClass::Dispenser => "dispenser",
+            EntityClass::Teleporter => "teleporter",
+            _ => return,
+        };
+        let (Some(owner), Some(state)) = (e.owner(), BuildingState::of(e)) else {
+            return;
+        };
+        let origin = state.origin;
+        let Some(builder) = self
+            .entity_handles
+            .get(&owner)
+            .and_then(|eid| self.get_player(eid))
+        else {
+            error!("Could not find player entity that placed {kind} {owner}");
+            return;
+        };
+        let team = builder.team;
+        let Some(steamid) = self.user_id_to_steam_id.get(&builder.user_id).cloned() else {
+            error!("Could not find steamid for player that placed {kind} {owner}");
+            return;
+        };
+
+        let region = self.region_at(&origin);
+        self.buildings.handle_built(BuildingRecord::new(
+            id,
+            steamid.clone(),
+            team,
+            self.tick,
+            &state,
+            region.clone(),
+        ));
+
+        if self.options.map_events {
+            self.current_round.map_events.push(MapEvent {
+                tick: self.tick,
+                kind: MapEventKind::Building,
+                team,
+                player: steamid,
+                origin,
+                region,
+                detail: Some(kind.to_string()),
+            });
+        }
+    }
+
+    fn steamid_for_handle(&self, handle: &u32) -> Option<&String> {
+        self.entity_handles
+            .get(handle)
+            .and_then(|eid| self.user_entities.get(eid))
+            .and_then(|uid| self.user_id_to_steam_id.get(uid))
+    }
+
+    fn sample_positions(&mut self) {
+        let Some(mut positions) = self.positions.take() else {
+            return;
+        };
+
+        if positions.take_sample(self.tick) {
+            for (i, e) in self.entities.iter().enumerate() {
+                let Some(e) = e else {
+                    continue;
+                };
+                let id = EntityId::from(i as u32);
+
+                if let Some(player) = e.player() {
+                    if player.team != Team::Red && player.team != Team::Blue {
+                        continue;
+                    }
+                    let Some(steamid) = self.user_id_to_steam_id.get(&player.user_id) else {
+                        continue;
+                    };
+                    positions.record_player(steamid, self.tick, player);
+                } else if let Some(p) = e.projectile() {
+                    let owner = self.steamid_for_handle(&p.owner).cloned();
+                    positions
+                        .projectile(id, || {
+                            ObjectTrack::new(format!("{:?}", p.kind), owner, p.team)
+                        })
+                        .push(self.tick, p.origin);
+                } else if let (Some(owner), Some(origin)) = (e.owner(), e.origin()) {
+                    let (kind, level) = if let Some(b) = e.sentry() {
+                        ("sentry", b.level)
+                    } else if let Some(b) = e.dispenser() {
+                        ("dispenser", b.level)
+                    } else if let Some(b) = e.teleporter() {
+                        ("teleporter", b.level)
+                    } else {
+                        continue;
+                    };
+                    let steamid = self.steamid_for_handle(&owner).cloned();
+                    let team = self
+                        .entity_handles
+                        .get(&owner)
+                        .and_then(|eid| self.get_player(eid))
+                        .map(|p| p.team)
+                        .unwrap_or_default();
+                    let track = positions
+                        .building(id, || ObjectTrack::new(kind.to_string(), steamid, team));
+                    track.push(self.tick, origin);
+                    track.level.push(level);
+                }
+            }
+        }
+
+        for eid in &self.deleted_entities {
+            positions.forget(eid);
+        }
+        self.positions = Some(positions);
+    }
+
     fn handle_user_message(&mut self, msg: &UserMessage) {
         match msg {
             UserMessage::SayText2(msg) => {
@@ -1552,6 +3384,7 @@ impl MessageHandler for MatchAnalyzer<'_> {
         matches!(
             message_type,
             MessageType::PacketEntities
+                | MessageType::ServerInfo
                 | MessageType::GameEvent
                 | MessageType::NetTick
                 | MessageType::TempEntities
@@ -1560,12 +3393,23 @@ impl MessageHandler for MatchAnalyzer<'_> {
     }
 
     fn handle_message(&mut self, message: &Message, tick: DemoTick, parser_state: &ParserState) {
+        self.interval_per_tick = parser_state.demo_meta.interval_per_tick;
         if tick != self.tick {
             self.handle_tick(&tick, None);
             self.tick = tick;
         }
         match message {
             Message::NetTick(t) => self.handle_tick(&tick, Some(t)),
+            Message::ServerInfo(info) => {
+                self.map = info.map.clone();
+                self.regions = self
+                    .options
+                    .regions
+                    .maps
+                    .get(&self.map)
+                    .map(|regions| RegionMap::new(regions.clone()));
+                self.load_geometry();
+            }
             Message::PacketEntities(message) => {
                 self.mutated_colliders.drain(..);
                 self.removed_colliders.drain(..);
@@ -1621,8 +3465,9 @@ impl MessageHandler for MatchAnalyzer<'_> {
                                 };
                                 if p.class == Class::Pyro
                                     && p.active_weapon_handle == p.weapon_handles[0]
+                                    && self.airblasts.insert(player)
                                 {
-                                    self.airblasts.insert(player);
+                                    self.handle_airblast(&player);
                                 }
                             } else {
                                 trace!("Unhandled animation type {event:?}: {te:?}");
@@ -1700,6 +3545,7 @@ impl MessageHandler for MatchAnalyzer<'_> {
 
                         if let Some(sentry) = ent.sentry() {
                             self.sentry_shots.push(SentryShot {
+                                id: EntityId::from(entity),
                                 sentry: sentry.clone(),
                             });
                         }
@@ -1790,6 +3636,7 @@ impl MessageHandler for MatchAnalyzer<'_> {
                     self.tick_events.push(Event::Hurt(hurt.clone()));
                 }
 
+                GameEvent::PlayerExtinguished(e) => self.handle_player_extinguished(e),
                 GameEvent::TeamPlayPointCaptured(cap) => self.handle_point_captured(cap),
                 GameEvent::TeamPlayCaptureBlocked(block) => self.handle_capture_blocked(block),
 
@@ -1869,6 +3716,8 @@ impl MessageHandler for MatchAnalyzer<'_> {
                         .players
                         .sort_by_cached_key(|p| p.steamid.clone());
 
+                    self.finish_fights();
+                    self.finish_buildings();
                     self.rounds.push(std::mem::take(&mut self.current_round));
 
                     // Reset stats for all players for the new round
@@ -1934,6 +3783,13 @@ impl MessageHandler for MatchAnalyzer<'_> {
                     }
 
                     let steamid = self.user_id_to_steam_id.get(&attacker_uid).cloned();
+                    let object = EntityId::from(e.index as u32);
+                    if e.object_type == OBJECT_TYPE_SAPPER {
+                        self.handle_sapper_removed(object, steamid.clone(), weapon);
+                    } else {
+                        self.handle_building_destroyed(object, steamid.clone(), weapon);
+                    }
+
                     if let Some(steamid) = steamid {
                         if let Some(attacker) = self.player_summaries.get_mut(&steamid) {
                             attacker.handle_object_destroyed(weapon);
@@ -2060,6 +3916,14 @@ impl MessageHandler for MatchAnalyzer<'_> {
         // For now, we'll assume if self.current_round.time > 0 or if it's not default, it's a partial round.
         // A more robust check might be needed depending on how RoundSummary is populated.
         // Let's assume if there are any players, or if round time is set, it's a round.
+        let mut in_flight: Vec<_> = std::mem::take(&mut self.projectile_tracks)
+            .into_values()
+            .collect();
+        in_flight.sort_by_key(|t| t.spawn_tick);
+        for track in in_flight {
+            self.finish_projectile(track);
+        }
+
         if self.current_round.time > 0.0
             || !self.player_summaries.is_empty() && self.rounds.is_empty()
             || (self.round_state != RoundState::default()
@@ -2071,6 +3935,8 @@ impl MessageHandler for MatchAnalyzer<'_> {
             self.current_round
                 .players
                 .sort_by_cached_key(|p| p.steamid.clone());
+            self.finish_fights();
+            self.finish_buildings();
             self.rounds.push(std::mem::take(&mut self.current_round));
         }
 
@@ -2090,11 +3956,17 @@ impl MessageHandler for MatchAnalyzer<'_> {
         DemoSummary {
             rounds: self.rounds,
             chat: self.chat,
+            positions: self.positions,
+            heatmaps: self.engagements.map(|engagements| {
+                let overview = self.options.heatmaps.overviews.get(&self.map).cloned();
+                Heatmaps::new(self.map, overview, engagements)
+            }),
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_update, clippy::redundant_static_lifetimes)]
 mod tests {
     use super::*;
     use tf_demo_parser::{
@@ -2247,4 +4119,265 @@ mod tests {
             EntityId::from(57u32)
         );
     }
+
+    fn burn(crit: bool, mini_crit: bool) -> PlayerHurtEvent {
+        PlayerHurtEvent {
+            user_id: 2,
+            health: 100,
+            attacker: 3,
+            damage_amount: 4,
+            custom: 0,
+            show_disguised_crit: false,
+            crit,
+            mini_crit,
+            all_see_crit: false,
+            weapon_id: WeaponId::Flamethrower as u16,
+            bonus_effect: 0,
+        }
+    }
+
+    // Whether a flamethrower burn on the victim at `tick` is afterburn, counting it if so.
+    fn flamed(victim: &mut PlayerSummary, tick: u32, hurt: &PlayerHurtEvent) -> bool {
+        let tick = DemoTick::from(tick);
+        let flamethrower = WeaponId::Flamethrower;
+        let afterburn = is_afterburn(victim, hurt, DamageType::Burning, &flamethrower, tick, 33);
+        if afterburn {
+            victim.afterburn_tick = tick;
+        }
+        afterburn
+    }
+
+    #[test]
+    fn afterburn_lands_once_per_period_from_ignition() {
+        let mut victim = PlayerSummary {
+            ignited: DemoTick::from(100),
+            ..Default::default()
+        };
+        let hurt = burn(false, false);
+        let booked: Vec<_> = [110, 131, 135, 150, 164, 166, 170, 199]
+            .into_iter()
+            .map(|t| flamed(&mut victim, t, &hurt))
+            .collect();
+        assert_eq!(
+            booked,
+            [false, true, false, false, true, false, false, true]
+        );
+
+        // Checking doesn't count anything; only a hurt that is credited does.
+        let tick = DemoTick::from(232);
+        let flamethrower = WeaponId::Flamethrower;
+        assert!(is_afterburn(
+            &victim,
+            &hurt,
+            DamageType::Burning,
+            &flamethrower,
+            tick,
+            33
+        ));
+        assert!(is_afterburn(
+            &victim,
+            &hurt,
+            DamageType::Burning,
+            &flamethrower,
+            tick,
+            33
+        ));
+    }
+
+    #[test]
+    fn crits_are_never_afterburn() {
+        let mut victim = PlayerSummary {
+            ignited: DemoTick::from(100),
+            ..Default::default()
+        };
+        assert!(!flamed(&mut victim, 133, &burn(true, false)));
+        assert!(!flamed(&mut victim, 133, &burn(false, true)));
+        assert!(flamed(&mut victim, 133, &burn(false, false)));
+
+        // Other weapons only do fire damage through afterburn.
+        let tick = DemoTick::from(140);
+        let (hurt, flare_gun) = (burn(true, false), WeaponId::Flaregun);
+        assert!(is_afterburn(
+            &victim,
+            &hurt,
+            DamageType::Burning,
+            &flare_gun,
+            tick,
+            33
+        ));
+        assert!(!is_afterburn(
+            &victim,
+            &hurt,
+            DamageType::Normal,
+            &flare_gun,
+            tick,
+            33
+        ));
+    }
+
+    #[test]
+    fn reigniting_restarts_the_cadence() {
+        let mut victim = PlayerSummary {
+            ignited: DemoTick::from(100),
+            ..Default::default()
+        };
+        let hurt = burn(false, false);
+        assert!(flamed(&mut victim, 133, &hurt));
+
+        victim.ignited = DemoTick::from(140);
+        assert!(!flamed(&mut victim, 166, &hurt));
+        assert!(flamed(&mut victim, 173, &hurt));
+        assert!(!flamed(&mut victim, 175, &hurt));
+    }
+
+    fn airblast_at(tick: u32) -> PendingPush {
+        PendingPush {
+            pyro: UserId::from(3u16),
+            weapon: "flamethrower",
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            victim: EntityId::from(2u32),
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            tick: DemoTick::from(tick),
+        }
+    }
+
+    #[test]
+    fn airblasts_reach_players_in_front_and_in_range() {
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        assert!(PendingPush::in_reach(
+            origin,
+            0.0,
+            Vec3::new(100.0, 50.0, 0.0)
+        ));
+        assert!(PendingPush::in_reach(
+            origin,
+            90.0,
+            Vec3::new(0.0, 200.0, 0.0)
+        ));
+        assert!(!PendingPush::in_reach(
+            origin,
+            0.0,
+            Vec3::new(-100.0, 0.0, 0.0)
+        ));
+        assert!(!PendingPush::in_reach(
+            origin,
+            0.0,
+            Vec3::new(0.0, 100.0, 0.0)
+        ));
+        let beyond = weapon::AIRBLAST_RANGE + 1.0;
+        assert!(!PendingPush::in_reach(
+            origin,
+            0.0,
+            Vec3::new(beyond, 0.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn pushes_are_a_velocity_change_away_from_the_pyro() {
+        let push = airblast_at(100);
+        let victim = Vec3::new(100.0, 0.0, 0.0);
+        let fast = weapon::AIRBLAST_PUSH_SPEED * 2.0;
+        assert!(push.pushed(victim, Vec3::new(fast, 0.0, 0.0)));
+        // Too slow, or towards the pyro, is just the victim moving.
+        let slow = weapon::AIRBLAST_PUSH_SPEED / 2.0;
+        assert!(!push.pushed(victim, Vec3::new(slow, 0.0, 0.0)));
+        assert!(!push.pushed(victim, Vec3::new(-fast, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn pushes_wait_for_the_window() {
+        let push = airblast_at(100);
+        assert!(push.pending(DemoTick::from(100)));
+        assert!(push.pending(DemoTick::from(100 + weapon::AIRBLAST_PUSH_WINDOW - 1)));
+        assert!(!push.pending(DemoTick::from(100 + weapon::AIRBLAST_PUSH_WINDOW)));
+    }
+
+    #[test]
+    fn market_gardens_are_melee_kills_mid_jump() {
+        assert!(is_market_garden(true, WeaponId::Shovel as u16));
+        assert!(!is_market_garden(false, WeaponId::Shovel as u16));
+        assert!(!is_market_garden(true, WeaponId::Rocketlauncher as u16));
+    }
+
+    #[test]
+    fn matchups_add_up_per_player_and_per_class() {
+        let mut round = RoundSummary::default();
+        round.handle_matchup_hit("a", "b", Class::Soldier, Class::Scout);
+        round.handle_matchup_damage("a", "b", Class::Soldier, Class::Scout, 90);
+        round.handle_matchup_hit("a", "b", Class::Soldier, Class::Scout);
+        round.handle_matchup_damage("a", "b", Class::Soldier, Class::Scout, 35);
+        round.handle_matchup_kill("a", "b", Class::Soldier, Class::Scout);
+        round.handle_matchup_damage("c", "b", Class::Soldier, Class::Scout, 50);
+
+        let ab = &round.damage_matrix["a"]["b"];
+        assert_eq!((ab.damage, ab.hits, ab.kills), (125, 2, 1));
+        let cb = &round.damage_matrix["c"]["b"];
+        assert_eq!((cb.damage, cb.hits, cb.kills), (50, 0, 0));
+        assert!(!round.damage_matrix.contains_key("b"));
+
+        let soldier = Class::Soldier.to_string();
+        let scout = Class::Scout.to_string();
+        let classes = &round.class_matrix[&soldier][&scout];
+        assert_eq!((classes.damage, classes.hits, classes.kills), (175, 2, 1));
+    }
+
+    fn exit(x: f32, owner: u32) -> entity::Teleporter {
+        entity::Teleporter {
+            origin: Vec3::new(x, 0.0, 0.0),
+            owner,
+            exit: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn teleports_come_out_of_the_nearest_exit_in_range() {
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let near = exit(TELEPORT_RANGE / 2.0, 1);
+        let far = exit(TELEPORT_RANGE - 1.0, 2);
+        let entrance = entity::Teleporter {
+            exit: false,
+            ..exit(0.0, 3)
+        };
+        let carried = entity::Teleporter {
+            carried: true,
+            ..exit(0.0, 4)
+        };
+        let found = teleporter_exit([&far, &entrance, &carried, &near], &origin);
+        assert_eq!(found.map(|t| t.owner), Some(1));
+
+        let out_of_range = exit(TELEPORT_RANGE + 1.0, 5);
+        assert!(teleporter_exit([&entrance, &carried, &out_of_range], &origin).is_none());
+    }
+
+    #[test]
+    fn engineers_do_not_provide_their_own_teleports() {
+        let schema = Schema::default();
+        let mut analyzer = MatchAnalyzer::new(&schema);
+        for steamid in ["engineer", "scout"] {
+            analyzer
+                .player_summaries
+                .insert(steamid.to_string(), PlayerSummary::default());
+        }
+        analyzer.credit_teleport("scout", Some("engineer"));
+        analyzer.credit_teleport("engineer", Some("engineer"));
+        analyzer.credit_teleport("scout", None);
+
+        let stats = |steamid: &str| &analyzer.player_summaries[steamid].stats;
+        assert_eq!(stats("scout").teleports_taken, 2);
+        assert_eq!(stats("engineer").teleports_taken, 1);
+        assert_eq!(stats("engineer").teleports_provided, 1);
+    }
+
+    #[test]
+    fn dispenser_healing_is_capped_by_level() {
+        assert_eq!(dispenser_healing(1, 1.0, 50, 100), 10);
+        assert_eq!(dispenser_healing(2, 1.0, 50, 100), 15);
+        assert_eq!(dispenser_healing(3, 1.0, 50, 100), 20);
+        assert_eq!(dispenser_healing(3, 1.0, 95, 100), 5);
+        // Out of range levels are clamped.
+        assert_eq!(dispenser_healing(0, 2.0, 50, 100), 20);
+        // Losing health at a dispenser isn't negative healing.
+        assert_eq!(dispenser_healing(3, 1.0, 100, 60), 0);
+    }
 }
@@ -0,0 +1,250 @@
+use crate::{
+    Vec3,
+    parser::{
+        entity::{self, Projectile, ProjectileType},
+        is_false,
+    },
+};
+use rapier3d::prelude::{Aabb, BoundingVolume};
+use serde::{Deserialize, Serialize};
+use tf_demo_parser::demo::data::DemoTick;
+
+// Explosions this close to a player's hull count as landing on them rather than next to them.
+const DIRECT_HIT_TOLERANCE: f32 = 8.0;
+
+/// What ultimately happened to a projectile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ProjectileOutcome {
+    DirectHit,
+    Splash,
+    #[default]
+    Miss,
+    Reflected,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrajectorySample {
+    pub tick: DemoTick,
+    pub origin: Vec3,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectileHit {
+    pub victim: String, // steamid
+    pub damage: u32,
+    #[serde(skip_serializing_if = "is_false")]
+    pub direct: bool,
+    #[serde(skip)]
+    pub distance: f32, // from whoever fired it, when it landed
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ProjectileTrack {
+    pub owner: String, // steamid
+    pub weapon: String,
+    pub kind: ProjectileType,
+
+    pub spawn_tick: DemoTick,
+    pub spawn_origin: Vec3,
+    pub path: Vec<TrajectorySample>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detonation_tick: Option<DemoTick>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detonation_origin: Option<Vec3>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hits: Vec<ProjectileHit>,
+    pub outcome: ProjectileOutcome,
+
+    #[serde(skip)]
+    pub reflected: bool,
+}
+
+impl ProjectileTrack {
+    pub fn new(owner: String, weapon: &str, projectile: &Projectile, tick: DemoTick) -> Self {
+        Self {
+            owner,
+            weapon: weapon.into(),
+            kind: projectile.kind,
+            spawn_tick: tick,
+            spawn_origin: projectile.origin,
+            path: vec![TrajectorySample {
+                tick,
+                origin: projectile.origin,
+            }],
+            reflected: projectile.is_reflected,
+            ..Default::default()
+        }
+    }
+
+    pub fn sample(&mut self, projectile: &Projectile, tick: DemoTick) {
+        self.reflected |= projectile.is_reflected;
+
+        if self.path.last().map(|s| s.origin) != Some(projectile.origin) {
+            self.path.push(TrajectorySample {
+                tick,
+                origin: projectile.origin,
+            });
+        }
+    }
+
+    pub fn detonate(&mut self, tick: DemoTick) {
+        self.detonation_tick = Some(tick);
+        self.detonation_origin = self.path.last().map(|s| s.origin);
+    }
+
+    // Distinct players this projectile damaged.
+    pub fn victim_ids(&self) -> Vec<&str> {
+        let mut victims: Vec<_> = self.hits.iter().map(|h| h.victim.as_str()).collect();
+        victims.sort();
+        victims.dedup();
+        victims
+    }
+
+    pub fn victims(&self) -> u32 {
+        self.victim_ids().len() as u32
+    }
+
+    // How far away the closest player it hit was.
+    pub fn nearest_hit(&self) -> Option<f32> {
+        self.hits.iter().map(|h| h.distance).min_by(f32::total_cmp)
+    }
+
+    pub fn finish(&mut self) {
+        self.outcome = if self.reflected {
+            ProjectileOutcome::Reflected
+        } else if self.hits.iter().any(|h| h.direct) {
+            ProjectileOutcome::DirectHit
+        } else if !self.hits.is_empty() {
+            ProjectileOutcome::Splash
+        } else {
+            ProjectileOutcome::Miss
+        };
+    }
+}
+
+// Whether an exploding projectile went off against the victim's hull, as opposed to catching
+// them in the splash.
+pub fn is_direct_hit(projectile: &Projectile, victim_origin: &Vec3, victim_crouched: bool) -> bool {
+    if entity::is_sticky(projectile.kind) {
+        return false;
+    }
+
+    let hull = entity::player_hull(victim_crouched)
+        .compute_local_aabb()
+        .loosened(DIRECT_HIT_TOLERANCE);
+    let hull = Aabb::new(
+        victim_origin + hull.mins.coords,
+        victim_origin + hull.maxs.coords,
+    );
+
+    // The last update we saw may be from a tick before impact, so also check where it was headed.
+    [0.0, 0.5, 1.0]
+        .iter()
+        .any(|t| hull.contains_local_point(&(projectile.origin + projectile.velocity.coords * *t)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rocket(kind: ProjectileType, origin: [f32; 3], velocity: [f32; 3]) -> Projectile {
+        Projectile {
+            kind,
+            origin: Vec3::from(origin),
+            velocity: Vec3::from(velocity),
+            ..Default::default()
+        }
+    }
+
+    fn hit(victim: &str, direct: bool) -> ProjectileHit {
+        ProjectileHit {
+            victim: victim.into(),
+            damage: 50,
+            direct,
+            distance: 0.0,
+        }
+    }
+
+    #[test]
+    fn direct_hits_land_on_the_hull() {
+        let victim = Vec3::new(100.0, 100.0, 0.0);
+        let direct = |origin, velocity| {
+            is_direct_hit(
+                &rocket(ProjectileType::Rocket, origin, velocity),
+                &victim,
+                false,
+            )
+        };
+
+        assert!(direct([100.0, 100.0, 40.0], [0.0; 3]));
+        assert!(direct([130.0, 100.0, 40.0], [0.0; 3]));
+        assert!(!direct([160.0, 100.0, 40.0], [0.0; 3]));
+        assert!(!direct([100.0, 100.0, -20.0], [0.0; 3]));
+        // Still on its way in when last seen.
+        assert!(direct([100.0, 180.0, 40.0], [0.0, -100.0, 0.0]));
+    }
+
+    #[test]
+    fn crouching_players_are_shorter() {
+        let victim = Vec3::new(0.0, 0.0, 0.0);
+        let above = rocket(ProjectileType::Rocket, [0.0, 0.0, 80.0], [0.0; 3]);
+        assert!(is_direct_hit(&above, &victim, false));
+        assert!(!is_direct_hit(&above, &victim, true));
+    }
+
+    #[test]
+    fn stickies_never_hit_directly() {
+        let victim = Vec3::new(0.0, 0.0, 0.0);
+        let sticky = rocket(ProjectileType::StickyBomb, [0.0, 0.0, 40.0], [0.0; 3]);
+        assert!(!is_direct_hit(&sticky, &victim, false));
+    }
+
+    #[test]
+    fn counts_distinct_victims() {
+        let mut track = ProjectileTrack::default();
+        assert_eq!(track.victims(), 0);
+        track.hits = vec![hit("b", true), hit("a", false), hit("b", false)];
+        assert_eq!(track.victims(), 2);
+    }
+
+    #[test]
+    fn ranges_by_the_nearest_victim() {
+        let mut track = ProjectileTrack::default();
+        assert_eq!(track.nearest_hit(), None);
+        track.hits = [("a", 900.0), ("b", 300.0), ("c", 600.0)]
+            .map(|(victim, distance)| ProjectileHit {
+                distance,
+                ..hit(victim, false)
+            })
+            .into();
+        assert_eq!(track.nearest_hit(), Some(300.0));
+    }
+
+    #[test]
+    fn finishes_with_an_outcome() {
+        let outcome = |hits, reflected| {
+            let mut track = ProjectileTrack {
+                hits,
+                reflected,
+                ..Default::default()
+            };
+            track.finish();
+            track.outcome
+        };
+        assert_eq!(outcome(vec![], false), ProjectileOutcome::Miss);
+        assert_eq!(
+            outcome(vec![hit("a", false)], false),
+            ProjectileOutcome::Splash
+        );
+        assert_eq!(
+            outcome(vec![hit("a", false), hit("b", true)], false),
+            ProjectileOutcome::DirectHit
+        );
+        assert_eq!(
+            outcome(vec![hit("a", true)], true),
+            ProjectileOutcome::Reflected
+        );
+    }
+}
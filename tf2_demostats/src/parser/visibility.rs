@@ -0,0 +1,202 @@
+use crate::{
+    Vec3,
+    parser::{entity::Player, summarizer::WORLD_GROUP},
+};
+use parry3d::math::Vector;
+use rapier3d::prelude::{
+    ColliderSet, Group, InteractionGroups, QueryFilter, QueryPipeline, Ray, RigidBodySet,
+};
+use tf_demo_parser::demo::parser::gamestateanalyser::Team;
+
+// Heights above a player's origin, which is at their feet.
+const EYE_HEIGHT: f32 = 68.0;
+const CROUCHED_EYE_HEIGHT: f32 = 45.0;
+const BODY_HEIGHT: f32 = 41.0;
+const CROUCHED_BODY_HEIGHT: f32 = 31.0;
+
+/// A living player, as far as seeing and being seen goes.
+pub struct Viewer {
+    pub steamid: String,
+    pub team: Team,
+    eye: Vec3,
+    body: Vec3,
+    look: Vector<f32>, // unit vector
+}
+
+impl Viewer {
+    pub fn new(steamid: String, player: &Player) -> Self {
+        let (eye, body) = if player.ducked {
+            (CROUCHED_EYE_HEIGHT, CROUCHED_BODY_HEIGHT)
+        } else {
+            (EYE_HEIGHT, BODY_HEIGHT)
+        };
+        let (pitch, yaw) = (player.eye.x.to_radians(), player.eye.y.to_radians());
+        Self {
+            steamid,
+            team: player.team,
+            eye: player.origin + Vector::new(0.0, 0.0, eye),
+            body: player.origin + Vector::new(0.0, 0.0, body),
+            // Source pitch is positive looking down.
+            look: Vector::new(
+                pitch.cos() * yaw.cos(),
+                pitch.cos() * yaw.sin(),
+                -pitch.sin(),
+            ),
+        }
+    }
+
+    fn facing(&self, point: &Vec3, cos_half_fov: f32) -> bool {
+        (point - self.eye).normalize().dot(&self.look) >= cos_half_fov
+    }
+}
+
+// Whether nothing in the level is between two points.
+fn clear_line(
+    world: &QueryPipeline,
+    collider_set: &ColliderSet,
+    rigid_body_set: &RigidBodySet,
+    from: &Vec3,
+    to: &Vec3,
+) -> bool {
+    let ray = Ray::new(*from, to - from);
+    let filter = QueryFilter::new().groups(InteractionGroups::new(Group::ALL, WORLD_GROUP));
+    world
+        .cast_ray(rigid_body_set, collider_set, &ray, 1.0, true, filter)
+        .is_none()
+}
+
+/// Every (viewer, target) pair of indices into `viewers` where the target is an enemy the viewer
+/// is facing with nothing solid in the way. `fov` is in degrees, as a cone around where the
+/// viewer is looking.
+pub fn sightings(
+    world: &QueryPipeline,
+    collider_set: &ColliderSet,
+    rigid_body_set: &RigidBodySet,
+    viewers: &[Viewer],
+    fov: f32,
+) -> Vec<(usize, usize)> {
+    let cos_half_fov = (fov / 2.0).to_radians().cos();
+    let mut sightings = vec![];
+    for (v, viewer) in viewers.iter().enumerate() {
+        for (t, target) in viewers.iter().enumerate() {
+            if target.team == viewer.team {
+                continue;
+            }
+            // Seeing either their head or their body is enough.
+            let seen = [&target.eye, &target.body].into_iter().any(|point| {
+                viewer.facing(point, cos_half_fov)
+                    && clear_line(world, collider_set, rigid_body_set, &viewer.eye, point)
+            });
+            if seen {
+                sightings.push((v, t));
+            }
+        }
+    }
+    sightings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec2;
+    use rapier3d::prelude::ColliderBuilder;
+
+    const FOV: f32 = 90.0;
+
+    struct World {
+        pipeline: QueryPipeline,
+        colliders: ColliderSet,
+        bodies: RigidBodySet,
+    }
+
+    impl World {
+        // The level, with an optional wall across the x axis at `wall_x`.
+        fn new(wall_x: Option<f32>) -> Self {
+            let mut world = Self {
+                pipeline: QueryPipeline::new(),
+                colliders: ColliderSet::new(),
+                bodies: RigidBodySet::new(),
+            };
+            if let Some(x) = wall_x {
+                let vertices = vec![
+                    Vec3::new(x, -1000.0, -1000.0),
+                    Vec3::new(x, 1000.0, -1000.0),
+                    Vec3::new(x, 0.0, 1000.0),
+                ];
+                let c = ColliderBuilder::trimesh(vertices, vec![[0, 1, 2]])
+                    .unwrap()
+                    .collision_groups(InteractionGroups::new(WORLD_GROUP, Group::ALL))
+                    .build();
+                let k = world.colliders.insert(c);
+                world
+                    .pipeline
+                    .update_incremental(&world.colliders, &[k], &[], true);
+            }
+            world
+        }
+
+        fn sightings(&self, viewers: &[Viewer]) -> Vec<(usize, usize)> {
+            sightings(&self.pipeline, &self.colliders, &self.bodies, viewers, FOV)
+        }
+    }
+
+    fn viewer(steamid: &str, team: Team, origin: Vec3, yaw: f32) -> Viewer {
+        let player = Player {
+            team,
+            origin,
+            eye: Vec2::new(0.0, yaw),
+            ..Default::default()
+        };
+        Viewer::new(steamid.into(), &player)
+    }
+
+    #[test]
+    fn facing_is_a_cone_around_the_look_direction() {
+        let v = viewer("r", Team::Red, Vec3::new(0.0, 0.0, 0.0), 0.0);
+        let cos_half_fov = (FOV / 2.0).to_radians().cos();
+        let at = |degrees: f32| {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            v.eye + Vector::new(100.0 * cos, 100.0 * sin, 0.0)
+        };
+        assert!(v.facing(&at(0.0), cos_half_fov));
+        assert!(v.facing(&at(44.0), cos_half_fov));
+        assert!(v.facing(&at(-44.0), cos_half_fov));
+        assert!(!v.facing(&at(46.0), cos_half_fov));
+        assert!(!v.facing(&at(180.0), cos_half_fov));
+    }
+
+    #[test]
+    fn enemies_facing_each_other_in_the_open_see_each_other() {
+        let viewers = [
+            viewer("r", Team::Red, Vec3::new(0.0, 0.0, 0.0), 0.0),
+            viewer("b", Team::Blue, Vec3::new(500.0, 0.0, 0.0), 180.0),
+        ];
+        assert_eq!(World::new(None).sightings(&viewers), [(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn only_enemies_in_the_cone_are_seen() {
+        let viewers = [
+            viewer("r", Team::Red, Vec3::new(0.0, 0.0, 0.0), 0.0),
+            // Off to the side, looking away.
+            viewer("b", Team::Blue, Vec3::new(100.0, 200.0, 0.0), 90.0),
+            // A teammate straight ahead.
+            viewer("r2", Team::Red, Vec3::new(300.0, 0.0, 0.0), 180.0),
+        ];
+        assert!(World::new(None).sightings(&viewers).is_empty());
+    }
+
+    #[test]
+    fn level_geometry_blocks_sight() {
+        let viewers = [
+            viewer("r", Team::Red, Vec3::new(0.0, 0.0, 0.0), 0.0),
+            viewer("b", Team::Blue, Vec3::new(500.0, 0.0, 0.0), 180.0),
+        ];
+        assert!(World::new(Some(250.0)).sightings(&viewers).is_empty());
+        // A wall behind them both doesn't matter.
+        assert_eq!(
+            World::new(Some(750.0)).sightings(&viewers),
+            [(0, 1), (1, 0)]
+        );
+    }
+}
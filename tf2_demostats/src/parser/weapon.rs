@@ -1,7 +1,7 @@
 use crate::{
     parser::{
         entity::{self, ProjectileType},
-        game::{DamageType, GrenadeType},
+        game::{DamageType, GrenadeType, WeaponId},
     },
     schema,
 };
@@ -28,6 +28,15 @@ pub enum Weapon {
     ShotgunPyro,
 }
 
+// The airblast hitbox is a 256 unit cube in front of the pyro; anyone further than this can't have
+// been pushed.
+pub const AIRBLAST_RANGE: f32 = 256.0;
+// Change in velocity (units per tick) for a player in front of an airblast to count as pushed,
+// roughly 250u/s on a 66 tick server.
+pub const AIRBLAST_PUSH_SPEED: f32 = 4.0;
+// Ticks after an airblast to wait for the push to show up in the victim's movement.
+pub const AIRBLAST_PUSH_WINDOW: u32 = 8;
+
 pub fn strip_prefix(killer_weapon_name: &str) -> &str {
     let prefixes = ["tf_weapon_grenade_", "tf_weapon_", "NPC_", "func_"];
 
@@ -58,10 +67,36 @@ pub fn sentry_name(sentry: &entity::Sentry) -> &'static str {
 pub fn is_sentry(name: &'static str) -> bool {
     matches!(
         name,
-        "obj_minisentry" | "obj_sentrygun" | "obj_sentrygun2" | "obj_sentrygun3"
+        "obj_minisentry" | "obj_sentrygun" | "obj_sentrygun2" | "obj_sentrygun3" | "wrangler_kill"
     )
 }
 
+/// How a sentry hit someone.
+#[derive(Clone, Copy, Debug)]
+pub struct SentryFire {
+    pub wrangled: bool,
+    pub rocket: bool,
+}
+
+pub fn sentry_fire(damage_type: DamageType, weapon_id: u16) -> SentryFire {
+    SentryFire {
+        wrangled: damage_type == DamageType::PlayerSentry,
+        rocket: WeaponId::try_from(weapon_id).is_ok_and(|w| w == WeaponId::SentryRocket),
+    }
+}
+
+// Kills with a projectile that was reflected by the killer.
+pub fn is_reflect(log_name: &str) -> bool {
+    log_name.starts_with("deflect_") || log_name == "loose_cannon_reflect"
+}
+
+pub fn causes_bleed(item: &schema::Item) -> bool {
+    item.attributes.values().any(|a| match a {
+        schema::Attribute::Float(f) => f.attribute_class == "bleeding_duration",
+        schema::Attribute::String(s) => s.attribute_class == "bleeding_duration",
+    })
+}
+
 pub fn weapon_name(weapon: &schema::Item, class: Class) -> &'static str {
     weapon
         .item_logname
@@ -258,3 +293,42 @@ pub fn projectile_explosion_radius(projectile_class_name: &str) -> f32 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_sentry_fire_apart() {
+        let fire = |damage_type, weapon: WeaponId| {
+            let fire = sentry_fire(damage_type, weapon as u16);
+            (fire.wrangled, fire.rocket)
+        };
+        assert_eq!(
+            fire(DamageType::Normal, WeaponId::SentryBullet),
+            (false, false)
+        );
+        assert_eq!(
+            fire(DamageType::Normal, WeaponId::SentryRocket),
+            (false, true)
+        );
+        assert_eq!(
+            fire(DamageType::PlayerSentry, WeaponId::SentryBullet),
+            (true, false)
+        );
+        assert_eq!(
+            fire(DamageType::PlayerSentry, WeaponId::SentryRocket),
+            (true, true)
+        );
+
+        let unknown = sentry_fire(DamageType::Normal, u16::MAX);
+        assert!(!unknown.wrangled && !unknown.rocket);
+    }
+
+    #[test]
+    fn wrangler_kills_are_sentry_kills() {
+        assert!(is_sentry("wrangler_kill"));
+        assert!(is_sentry("obj_minisentry"));
+        assert!(!is_sentry("wrangler"));
+    }
+}
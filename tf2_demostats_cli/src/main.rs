@@ -1,5 +1,6 @@
 use clap::{CommandFactory, Parser, Subcommand, ValueHint};
 use std::{
+    collections::HashMap,
     env,
     fs::File,
     io,
@@ -7,7 +8,8 @@ use std::{
     process::ExitCode,
 };
 use tf2_demostats::{
-    Result, parser,
+    Result,
+    parser::{self, heatmap::MapOverview, options::ParseOptions, render::render_round_svg},
     schema::{self, download_schema},
 };
 use tracing::{error, info};
@@ -38,9 +40,64 @@ enum Commands {
         #[arg(short, long, default_value = DEFAULT_SCHEMA)]
         schema: PathBuf,
 
+        #[arg(
+            short,
+            long,
+            value_hint = ValueHint::FilePath,
+            help = "JSON file of parse options"
+        )]
+        options: Option<PathBuf>,
+
+        #[arg(long, help = "Include every projectile's trajectory in the output")]
+        projectiles: bool,
+
+        #[arg(
+            long,
+            help = "Include sampled player, building and projectile positions"
+        )]
+        positions: bool,
+
+        #[arg(long, help = "Include kill and damage positions for heatmaps")]
+        heatmaps: bool,
+
+        #[arg(
+            long,
+            value_hint = ValueHint::FilePath,
+            help = "JSON file of named map regions, keyed by map name"
+        )]
+        regions: Option<PathBuf>,
+
+        #[arg(
+            long,
+            value_hint = ValueHint::DirPath,
+            help = "Directory of <map>.bsp files to load level geometry from"
+        )]
+        maps: Option<PathBuf>,
+
         #[arg(required=true, value_hint = ValueHint::FilePath, num_args = 1..)]
         demo: Vec<PathBuf>,
     },
+    #[command(about = "Render an SVG minimap of each round in a demo")]
+    Render {
+        #[arg(short, long, default_value = DEFAULT_SCHEMA)]
+        schema: PathBuf,
+
+        #[arg(
+            long,
+            value_hint = ValueHint::FilePath,
+            help = "JSON file of map overview transforms, keyed by map name"
+        )]
+        overviews: PathBuf,
+
+        #[arg(long, help = "Overview image to draw over, as a path or URL")]
+        image: String,
+
+        #[arg(short, long, value_hint = ValueHint::DirPath, default_value = ".")]
+        out: PathBuf,
+
+        #[arg(required=true, value_hint = ValueHint::FilePath)]
+        demo: PathBuf,
+    },
     #[command(about = "Update the local schema schema cache")]
     Update {
         #[arg(short, long, default_value = DEFAULT_SCHEMA)]
@@ -98,7 +155,38 @@ async fn exec() -> Result<()> {
         .init();
     match args.command {
         Commands::Version => cmd_version().await,
-        Commands::Parse { schema, demo } => cmd_parse(&schema, demo).await,
+        Commands::Parse {
+            schema,
+            options,
+            projectiles,
+            positions,
+            heatmaps,
+            regions,
+            maps,
+            demo,
+        } => {
+            let mut options = match options {
+                Some(path) => serde_json::from_slice(&tokio::fs::read(path).await?)?,
+                None => ParseOptions::default(),
+            };
+            options.projectiles |= projectiles;
+            options.positions.enabled |= positions;
+            options.heatmaps.enabled |= heatmaps;
+            if let Some(path) = regions {
+                options.regions.maps = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+            }
+            if maps.is_some() {
+                options.geometry.maps_dir = maps;
+            }
+            cmd_parse(&schema, &options, demo).await
+        }
+        Commands::Render {
+            schema,
+            overviews,
+            image,
+            out,
+            demo,
+        } => cmd_render(&schema, &overviews, &image, &out, &demo).await,
         Commands::Update {
             schema,
             api_key: key,
@@ -122,14 +210,19 @@ async fn cmd_serve(schema_path: &Path, host: String, port: u16) -> Result<()> {
     Ok(())
 }
 
-async fn cmd_parse(schema_path: &Path, demo_paths: Vec<PathBuf>) -> Result<()> {
+async fn cmd_parse(
+    schema_path: &Path,
+    options: &ParseOptions,
+    demo_paths: Vec<PathBuf>,
+) -> Result<()> {
     let schema = schema::read(schema_path).await?;
 
     for mut demo_path in demo_paths {
         let path = demo_path.as_path();
         let bytes = tokio::fs::read(path).await?;
 
-        let mut demo = parser::parse(&bytes, &schema).expect("Demo should parse");
+        let mut demo =
+            parser::parse_with_options(&bytes, &schema, options).expect("Demo should parse");
         demo.filename = Some(String::from(demo_path.to_str().unwrap()));
         demo_path.add_extension("json");
         let mut out_file = File::create(demo_path)?;
@@ -139,6 +232,36 @@ async fn cmd_parse(schema_path: &Path, demo_paths: Vec<PathBuf>) -> Result<()> {
     Ok(())
 }
 
+async fn cmd_render(
+    schema_path: &Path,
+    overviews_path: &Path,
+    image: &str,
+    out_dir: &Path,
+    demo_path: &Path,
+) -> Result<()> {
+    let schema = schema::read(schema_path).await?;
+    let overviews: HashMap<String, MapOverview> =
+        serde_json::from_slice(&tokio::fs::read(overviews_path).await?)?;
+
+    let bytes = tokio::fs::read(demo_path).await?;
+    let options = ParseOptions {
+        map_events: true,
+        ..Default::default()
+    };
+    let demo = parser::parse_with_options(&bytes, &schema, &options)?;
+    let Some(overview) = overviews.get(&demo.header.map) else {
+        return Err(format!("No overview for map {}", demo.header.map).into());
+    };
+
+    for (i, round) in demo.summary.rounds.iter().enumerate() {
+        let path = out_dir.join(format!("round_{}.svg", i + 1));
+        tokio::fs::write(&path, render_round_svg(round, overview, image)).await?;
+        info!("Rendered {}", path.display());
+    }
+
+    Ok(())
+}
+
 async fn cmd_version() -> Result<()> {
     println!("tf2_demostats {}", env!("CARGO_PKG_VERSION"));
 